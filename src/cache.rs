@@ -0,0 +1,207 @@
+use anyhow::{Result, anyhow};
+use std::{
+    collections::{BTreeSet, HashSet},
+    env, fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use crate::{lockfile::Lockfile, modrinth::sha512};
+
+/// Lists every lockfile podzol has written, one absolute path per line
+const LOCKFILES: &str = "lockfiles";
+
+/// A content addressed store of downloaded files, keyed by their sha512 hash
+#[derive(Clone)]
+pub struct Cache {
+    root: PathBuf,
+}
+
+pub struct Entry {
+    pub sha512: String,
+    pub size: u64,
+}
+
+impl Cache {
+    /// Opens the cache in `PODZOL_CACHE_DIR` or the platform cache directory
+    /// (`$XDG_CACHE_HOME/podzol` on Linux)
+    pub fn new() -> Result<Self> {
+        let root = match env::var_os("PODZOL_CACHE_DIR") {
+            Some(root) => PathBuf::from(root),
+            None => dirs::cache_dir()
+                .ok_or_else(|| anyhow!("Failed to find the cache directory"))?
+                .join("podzol"),
+        };
+
+        Ok(Self { root })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn files(&self) -> PathBuf {
+        self.root.join("files")
+    }
+
+    /// Where a file is stored. Hashes come from lockfiles that may have been tampered with,
+    /// so anything but a sha512 hex digest is rejected before it becomes a path.
+    fn path(&self, sha512: &str) -> Result<PathBuf> {
+        if !is_sha512(sha512) {
+            return Err(anyhow!("Invalid sha512 hash '{sha512}'"));
+        }
+
+        let (prefix, rest) = sha512.split_at(2);
+        Ok(self.files().join(prefix).join(rest))
+    }
+
+    /// Returns the cached file, dropping it if it got corrupted
+    pub fn get(&self, sha512: &str) -> Result<Option<Vec<u8>>> {
+        let path = self.path(sha512)?;
+
+        let data = match fs::read(&path) {
+            Ok(data) => data,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+
+        if self::sha512(&data) != sha512 {
+            fs::remove_file(path)?;
+            return Ok(None);
+        }
+
+        Ok(Some(data))
+    }
+
+    pub fn insert(&self, sha512: &str, data: &[u8]) -> Result<()> {
+        let path = self.path(sha512)?;
+        let parent = path.parent().expect("Cache entries are always nested");
+        fs::create_dir_all(parent)?;
+
+        // Written next to the final location and renamed so readers never see partial files
+        let partial = path.with_extension("partial");
+        fs::write(&partial, data)?;
+        fs::rename(partial, path)?;
+
+        Ok(())
+    }
+
+    pub fn entries(&self) -> Result<Vec<Entry>> {
+        let mut entries = Vec::new();
+
+        let prefixes = match fs::read_dir(self.files()) {
+            Ok(prefixes) => prefixes,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(entries),
+            Err(err) => return Err(err.into()),
+        };
+
+        for prefix in prefixes {
+            let prefix = prefix?;
+
+            if !prefix.file_type()?.is_dir() {
+                continue;
+            }
+
+            for file in fs::read_dir(prefix.path())? {
+                let file = file?;
+                let rest = file.file_name().to_string_lossy().into_owned();
+
+                let sha512 = format!("{}{rest}", prefix.file_name().to_string_lossy());
+
+                // Partial writes and anything else that isn't an entry are never touched
+                if !is_sha512(&sha512) {
+                    continue;
+                }
+
+                entries.push(Entry {
+                    sha512,
+                    size: file.metadata()?.len(),
+                });
+            }
+        }
+
+        entries.sort_by(|a, b| a.sha512.cmp(&b.sha512));
+
+        Ok(entries)
+    }
+
+    pub fn remove(&self, sha512: &str) -> Result<()> {
+        match fs::remove_file(self.path(sha512)?) {
+            Err(err) if err.kind() != ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Removes every cached file
+    pub fn clean(&self) -> Result<()> {
+        match fs::remove_dir_all(self.files()) {
+            Err(err) if err.kind() != ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Remembers a lockfile so garbage collection keeps the files it references
+    pub fn register_lockfile(&self, path: &Path) -> Result<()> {
+        let path = fs::canonicalize(path)?;
+
+        let mut lockfiles = self.lockfiles()?;
+        if lockfiles.insert(path) {
+            self.write_lockfiles(&lockfiles)?;
+        }
+
+        Ok(())
+    }
+
+    fn lockfiles(&self) -> Result<BTreeSet<PathBuf>> {
+        match fs::read_to_string(self.root.join(LOCKFILES)) {
+            Ok(lockfiles) => Ok(lockfiles.lines().map(PathBuf::from).collect()),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(BTreeSet::new()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    fn write_lockfiles(&self, lockfiles: &BTreeSet<PathBuf>) -> Result<()> {
+        fs::create_dir_all(&self.root)?;
+
+        let contents: String = lockfiles
+            .iter()
+            .map(|path| format!("{}\n", path.display()))
+            .collect();
+        fs::write(self.root.join(LOCKFILES), contents)?;
+
+        Ok(())
+    }
+
+    /// Reads every known lockfile, forgetting the ones that no longer exist
+    pub fn known_lockfiles(&self) -> Result<Vec<Lockfile>> {
+        let mut paths = self.lockfiles()?;
+        paths.retain(|path| path.exists());
+        self.write_lockfiles(&paths)?;
+
+        paths
+            .iter()
+            .map(|path| {
+                Lockfile::read(path)
+                    .map_err(|err| anyhow!("Failed to read {}: {err}", path.display()))
+            })
+            .collect()
+    }
+
+    /// Every sha512 referenced by a known lockfile
+    pub fn referenced(&self) -> Result<HashSet<String>> {
+        Ok(self
+            .known_lockfiles()?
+            .iter()
+            .flat_map(|lockfile| lockfile.packages())
+            .map(|(_, _, package)| package.sha512.clone())
+            .collect())
+    }
+}
+
+/// Whether `hash` is a lowercase hex sha512 digest
+fn is_sha512(hash: &str) -> bool {
+    hash.len() == 128
+        && hash
+            .bytes()
+            .all(|byte| matches!(byte, b'0'..=b'9' | b'a'..=b'f'))
+}
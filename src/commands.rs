@@ -1,7 +1,29 @@
 mod add;
+mod cache;
+mod check;
 mod export;
+mod import;
 mod init;
+mod install;
+mod loader;
+mod matrix;
+mod migrate;
+mod outdated;
+mod remove;
+mod search;
+mod update;
 
 pub use add::add;
-pub use export::export;
+pub use cache::cache;
+pub use check::check;
+pub use export::{ExportFormat, export};
+pub use import::import;
 pub use init::{init, init_interactive};
+pub use install::install;
+pub use loader::loader;
+pub use matrix::matrix;
+pub use migrate::migrate;
+pub use outdated::outdated;
+pub use remove::remove;
+pub use search::search;
+pub use update::update;
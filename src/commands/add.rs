@@ -1,41 +1,383 @@
-use anyhow::Result;
-use std::fs;
+use anyhow::{Result, anyhow};
+use inquire::Confirm;
+use itertools::Itertools;
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fs,
+    io::{self, IsTerminal},
+};
 use toml_edit::{DocumentMut, InlineTable};
 
-use crate::{ProjectType, manifest::Manifest, modrinth::Client};
+use crate::{
+    ProjectType,
+    conflicts::ensure_compatible,
+    lockfile::{LOCKFILE, Lockfile, Package},
+    manifest::{Manifest, Strategy},
+    modrinth::DependencyType,
+    registry::Registry,
+};
 
-pub async fn add(client: &Client, projects: Vec<String>, project_type: ProjectType) -> Result<()> {
+/// A project waiting to be added, either requested directly or pulled in by a dependency
+struct Pending {
+    project: String,
+    version: Option<String>,
+    project_type: ProjectType,
+    required_by: Option<String>,
+    optional: bool,
+}
+
+pub async fn add(
+    client: &impl Registry,
+    projects: Vec<String>,
+    project_type: ProjectType,
+    strategy: Option<Strategy>,
+    optional: Option<bool>,
+) -> Result<()> {
     let manifest_src = fs::read_to_string("podzol.toml")?;
     let mut document: DocumentMut = manifest_src.parse()?;
+    let mut lockfile = Lockfile::read(LOCKFILE)?;
+
+    add_projects(
+        client,
+        &mut document,
+        &mut lockfile,
+        projects,
+        project_type,
+        strategy,
+        optional,
+    )
+    .await?;
+
+    ensure_compatible(client, &lockfile).await?;
+
+    fs::write("podzol.toml", document.to_string())?;
+    lockfile.write(LOCKFILE)?;
+
+    Ok(())
+}
+
+/// Adds projects and everything they depend on to the manifest `document` and the lockfile
+async fn add_projects(
+    client: &impl Registry,
+    document: &mut DocumentMut,
+    lockfile: &mut Lockfile,
+    projects: Vec<String>,
+    project_type: ProjectType,
+    strategy: Option<Strategy>,
+    optional: Option<bool>,
+) -> Result<()> {
     let manifest: Manifest = toml_edit::de::from_document(document.clone())?;
+    lockfile.retarget(&manifest.enviroment);
+
+    // Project IDs that are already part of the pack or were handled during this run
+    let mut seen: HashSet<String> = ProjectType::ALL
+        .into_iter()
+        .flat_map(|project_type| lockfile.section(project_type).values())
+        .map(|package| package.project_id.clone())
+        .collect();
+
+    let mut queue: VecDeque<_> = projects
+        .into_iter()
+        .map(|project| Pending {
+            project,
+            version: None,
+            project_type,
+            required_by: None,
+            optional: false,
+        })
+        .collect();
+
+    // Versions looked up ahead of time by ID, each round fetches all of its pinned ones at once
+    let mut versions = HashMap::new();
+
+    while !queue.is_empty() {
+        let round: Vec<_> = queue.drain(..).collect();
+
+        let ids: Vec<_> = round
+            .iter()
+            .map(|pending| pending.project.clone())
+            .unique()
+            .collect();
+        let projects = client.get_projects(&ids).await?;
 
-    for name in projects {
-        let project = client.get_project(&name).await?;
-
-        let versions = client
-            .get_project_versions(
-                &name,
-                &manifest.enviroment.minecraft,
-                &manifest.enviroment.loaders,
-            )
-            .await?;
-
-        // FIXME: use a proper strategy to choose
-        let version = &versions[0];
-        let version_number = &version.version_number;
-
-        let mut mod_table = InlineTable::new();
-        mod_table.insert("version", version_number.into());
-        mod_table.insert("side", project.get_side().to_string().into());
-        document[project_type.as_table()][&name] = mod_table.into();
-
-        println!(
-            "Added {name} {version_number} to {}",
-            project_type.as_table()
+        let pinned: Vec<_> = round
+            .iter()
+            .filter_map(|pending| pending.version.clone())
+            .filter(|id| !versions.contains_key(id))
+            .unique()
+            .collect();
+        versions.extend(
+            client
+                .get_versions(&pinned)
+                .await?
+                .into_iter()
+                .map(|version| (version.id.clone(), version)),
         );
-    }
 
-    fs::write("podzol.toml", document.to_string())?;
+        // Dependencies that only name a version, their projects are looked up after the round
+        let mut unresolved = Vec::new();
+
+        for pending in round {
+            let project = projects
+                .iter()
+                .find(|project| {
+                    project.id == pending.project
+                        || project.slug.eq_ignore_ascii_case(&pending.project)
+                })
+                .ok_or_else(|| {
+                    anyhow!(
+                        "Project {} not found, private and draft projects are only visible with a Modrinth token",
+                        pending.project
+                    )
+                })?;
+
+            let (name, project_type) = match &pending.required_by {
+                Some(_) => {
+                    let already_present = seen.contains(&project.id)
+                        || ProjectType::ALL.into_iter().any(|project_type| {
+                            manifest.section(project_type).contains_key(&project.slug)
+                        });
+
+                    if already_present {
+                        continue;
+                    }
+
+                    (
+                        project.slug.clone(),
+                        ProjectType::from_modrinth(&project.project_type)
+                            .unwrap_or(pending.project_type),
+                    )
+                }
+                None => (pending.project, pending.project_type),
+            };
+
+            seen.insert(project.id.clone());
+
+            if pending.optional {
+                let required_by = pending.required_by.as_deref().unwrap_or_default();
+
+                let accepted = match optional {
+                    Some(optional) => optional,
+                    // Nobody is around to answer the prompt in CI or a pipe
+                    None if !io::stdin().is_terminal() => {
+                        println!("Skipping {name}, an optional dependency of {required_by}");
+                        false
+                    }
+                    None => Confirm::new(&format!(
+                        "{required_by} has an optional dependency on {name}, add it?"
+                    ))
+                    .with_default(false)
+                    .prompt()?,
+                };
+
+                if !accepted {
+                    continue;
+                }
+            }
+
+            // Only explicitly requested projects get the strategy passed on the command line
+            let strategy = strategy.as_ref().filter(|_| pending.required_by.is_none());
+
+            let version = match &pending.version {
+                Some(id) => match versions.remove(id) {
+                    Some(version) => version,
+                    None => client.get_version_by_id(id).await?,
+                },
+                None => {
+                    client
+                        .get_latest_version(
+                            &project.id,
+                            &manifest.enviroment.minecraft,
+                            &manifest.enviroment.loaders,
+                            &strategy
+                                .cloned()
+                                .or_else(|| manifest.pack.strategy.clone())
+                                .unwrap_or_default(),
+                        )
+                        .await?
+                }
+            };
+            let version_number = version.version_number.clone();
+            let side = project.get_side();
+
+            for dependency in &version.dependencies {
+                let optional = match dependency.dependency_type {
+                    DependencyType::Required => false,
+                    DependencyType::Optional => true,
+                    DependencyType::Incompatible | DependencyType::Embedded => continue,
+                };
+
+                let project = match (&dependency.project_id, &dependency.version_id) {
+                    (Some(project_id), _) => project_id.clone(),
+                    (None, Some(version_id)) => {
+                        unresolved.push((version_id.clone(), project_type, name.clone(), optional));
+                        continue;
+                    }
+                    (None, None) => continue,
+                };
+
+                if seen.contains(&project) {
+                    continue;
+                }
+
+                queue.push_back(Pending {
+                    project,
+                    version: dependency.version_id.clone(),
+                    project_type,
+                    required_by: Some(name.clone()),
+                    optional,
+                });
+            }
+
+            let mut mod_table = InlineTable::new();
+            mod_table.insert("version", version_number.as_str().into());
+            mod_table.insert("side", side.to_string().into());
+            if pending.required_by.is_some() {
+                mod_table.insert("dependency", true.into());
+            }
+            if let Some(strategy) = strategy {
+                mod_table.insert("strategy", strategy.to_string().into());
+            }
+            document[project_type.as_table()][&name] = mod_table.into();
+
+            lockfile
+                .section_mut(project_type)
+                .insert(name.clone(), Package::new(version, side)?);
+
+            match &pending.required_by {
+                Some(required_by) => println!(
+                    "Added {name} {version_number} to {} (dependency of {required_by})",
+                    project_type.as_table()
+                ),
+                None => println!(
+                    "Added {name} {version_number} to {}",
+                    project_type.as_table()
+                ),
+            }
+        }
+
+        let ids: Vec<_> = unresolved
+            .iter()
+            .map(|(version_id, ..)| version_id.clone())
+            .unique()
+            .collect();
+        versions.extend(
+            client
+                .get_versions(&ids)
+                .await?
+                .into_iter()
+                .map(|version| (version.id.clone(), version)),
+        );
+
+        for (version_id, project_type, required_by, optional) in unresolved {
+            let project = versions
+                .get(&version_id)
+                .map(|version| version.project_id.clone())
+                .ok_or_else(|| {
+                    anyhow!("Version {version_id}, a dependency of {required_by}, not found")
+                })?;
+
+            if seen.contains(&project) {
+                continue;
+            }
+
+            queue.push_back(Pending {
+                project,
+                version: Some(version_id),
+                project_type,
+                required_by: Some(required_by),
+                optional,
+            });
+        }
+    }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::fake::{Fake, dependency, version};
+
+    const MANIFEST: &str = r#"
+[pack]
+name = "test"
+version = "1.0.0"
+
+[enviroment]
+minecraft = "1.21.1"
+fabric = "0.16.10"
+
+[mods]
+"#;
+
+    fn client() -> Fake {
+        let mut sodium = version("s1", "AAA", "0.6.5", 0);
+        sodium.dependencies = vec![dependency(Some("BBB"), None, DependencyType::Required)];
+        // Pointing back at sodium by version alone closes the cycle through the other branch
+        let mut fabric_api = version("f1", "BBB", "0.100.0", 0);
+        fabric_api.dependencies = vec![dependency(None, Some("s1"), DependencyType::Required)];
+        let mut iris = version("i1", "CCC", "1.8.0", 0);
+        iris.dependencies = vec![dependency(Some("BBB"), None, DependencyType::Required)];
+
+        Fake::default()
+            .project("AAA", "sodium")
+            .project("BBB", "fabric-api")
+            .project("CCC", "iris")
+            .version(sodium)
+            .version(fabric_api)
+            .version(iris)
+    }
+
+    #[tokio::test]
+    async fn dependency_cycles_are_added_once() {
+        let mut document: DocumentMut = MANIFEST.parse().unwrap();
+        let mut lockfile = Lockfile::default();
+
+        add_projects(
+            &client(),
+            &mut document,
+            &mut lockfile,
+            vec!["sodium".to_string()],
+            ProjectType::Mod,
+            None,
+            Some(false),
+        )
+        .await
+        .unwrap();
+
+        let mods = document["mods"].as_table().unwrap();
+        assert_eq!(mods.len(), 2);
+        assert!(mods["sodium"].get("dependency").is_none());
+        assert_eq!(mods["fabric-api"]["dependency"].as_bool(), Some(true));
+        assert_eq!(
+            lockfile.mods.keys().collect::<Vec<_>>(),
+            ["fabric-api", "sodium"]
+        );
+    }
+
+    #[tokio::test]
+    async fn present_dependencies_are_left_alone() {
+        let src = format!("{MANIFEST}fabric-api = {{ version = \"0.99.0\", side = \"both\" }}\n");
+        let mut document: DocumentMut = src.parse().unwrap();
+        let mut lockfile = Lockfile::default();
+
+        add_projects(
+            &client(),
+            &mut document,
+            &mut lockfile,
+            vec!["iris".to_string()],
+            ProjectType::Mod,
+            None,
+            Some(false),
+        )
+        .await
+        .unwrap();
+
+        let mods = document["mods"].as_table().unwrap();
+        assert_eq!(mods.len(), 2);
+        assert_eq!(mods["fabric-api"]["version"].as_str(), Some("0.99.0"));
+        assert!(mods["fabric-api"].get("dependency").is_none());
+        assert_eq!(lockfile.mods.keys().collect::<Vec<_>>(), ["iris"]);
+    }
+}
@@ -0,0 +1,70 @@
+use anyhow::Result;
+use indicatif::HumanBytes;
+use std::collections::HashMap;
+
+use crate::{CacheCommand, cache::Cache};
+
+pub fn cache(command: CacheCommand) -> Result<()> {
+    let cache = Cache::new()?;
+
+    match command {
+        CacheCommand::List => {
+            let names = known_filenames(&cache)?;
+
+            for entry in cache.entries()? {
+                println!(
+                    "{}  {:>10}  {}",
+                    &entry.sha512[..16.min(entry.sha512.len())],
+                    HumanBytes(entry.size).to_string(),
+                    names.get(&entry.sha512).map_or("", String::as_str)
+                );
+            }
+        }
+        CacheCommand::Size => {
+            let entries = cache.entries()?;
+            let size: u64 = entries.iter().map(|entry| entry.size).sum();
+
+            println!(
+                "{} in {} file(s) at {}",
+                HumanBytes(size),
+                entries.len(),
+                cache.root().display()
+            );
+        }
+        CacheCommand::Clean => {
+            cache.clean()?;
+
+            println!("Cleaned {}", cache.root().display());
+        }
+        CacheCommand::Gc => {
+            let referenced = cache.referenced()?;
+
+            let mut removed = 0;
+            let mut freed = 0;
+
+            for entry in cache.entries()? {
+                if referenced.contains(&entry.sha512) {
+                    continue;
+                }
+
+                cache.remove(&entry.sha512)?;
+                removed += 1;
+                freed += entry.size;
+            }
+
+            println!("Removed {removed} file(s), freeing {}", HumanBytes(freed));
+        }
+    }
+
+    Ok(())
+}
+
+/// Filenames of the cached files as recorded by known lockfiles, keyed by sha512
+fn known_filenames(cache: &Cache) -> Result<HashMap<String, String>> {
+    Ok(cache
+        .known_lockfiles()?
+        .iter()
+        .flat_map(|lockfile| lockfile.packages())
+        .map(|(_, _, package)| (package.sha512.clone(), package.filename.clone()))
+        .collect())
+}
@@ -0,0 +1,389 @@
+use anyhow::{Result, anyhow};
+use futures_util::future::try_join_all;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+    fs,
+    ops::Range,
+    path::PathBuf,
+};
+use toml_edit::{ImDocument, Item};
+
+use crate::{
+    ProjectType,
+    conflicts::find_conflicts,
+    constraint::VersionReq,
+    loaders,
+    lockfile::{LOCKFILE, Lockfile, Package},
+    manifest::{FileLocation, Loader, Manifest, Side},
+    registry::Registry,
+};
+
+const MANIFEST: &str = "podzol.toml";
+
+/// Collects problems and prints them as diagnostics pointing into the manifest
+struct Report<'a> {
+    src: &'a str,
+    problems: usize,
+}
+
+impl<'a> Report<'a> {
+    const fn new(src: &'a str) -> Self {
+        Self { src, problems: 0 }
+    }
+
+    fn error(&mut self, message: impl Display, span: Option<Range<usize>>) {
+        self.problems += 1;
+
+        eprintln!("error: {message}");
+
+        let Some(span) = span else {
+            eprintln!("  --> {MANIFEST}\n");
+            return;
+        };
+
+        let start = span.start.min(self.src.len());
+        let line_start = self.src[..start].rfind('\n').map_or(0, |index| index + 1);
+        let line = self.src[line_start..].lines().next().unwrap_or_default();
+        let number = self.src[..start].matches('\n').count() + 1;
+        let column = self.src[line_start..start].chars().count() + 1;
+
+        let end = span.end.clamp(start, line_start + line.len());
+        let width = self.src[start..end].chars().count().max(1);
+        let gutter = number.to_string().len();
+
+        eprintln!("{:gutter$}--> {MANIFEST}:{number}:{column}", "");
+        eprintln!("{:gutter$} |", "");
+        eprintln!("{number} | {line}");
+        eprintln!(
+            "{:gutter$} | {:column$}{}\n",
+            "",
+            "",
+            "^".repeat(width),
+            column = column - 1
+        );
+    }
+
+    fn finish(self) -> Result<()> {
+        match self.problems {
+            0 => {
+                println!("No problems found");
+                Ok(())
+            }
+            problems => Err(anyhow!("Found {problems} problem(s) in {MANIFEST}")),
+        }
+    }
+}
+
+/// Span of the value at `path`, e.g. `["enviroment", "minecraft"]`
+fn value_span(document: &ImDocument<&str>, path: &[&str]) -> Option<Range<usize>> {
+    let mut item = document.as_item();
+
+    for key in path {
+        item = item.get(key)?;
+    }
+
+    item.span()
+}
+
+/// Span of the key `key` in the table `table`
+fn key_span(document: &ImDocument<&str>, table: &str, key: &str) -> Option<Range<usize>> {
+    document
+        .get(table)?
+        .as_table_like()?
+        .get_key_value(key)?
+        .0
+        .span()
+}
+
+/// Whether files for these two sides end up in the same installation
+fn shares(side: &Side, other: &Side) -> bool {
+    side == other || *side == Side::Both || *other == Side::Both
+}
+
+pub async fn check(client: &impl Registry) -> Result<()> {
+    let src = fs::read_to_string(MANIFEST)?;
+    let mut report = Report::new(&src);
+
+    let document = match ImDocument::parse(src.as_str()) {
+        Ok(document) => document,
+        Err(err) => {
+            report.error(err.message().trim_end(), err.span());
+            return report.finish();
+        }
+    };
+
+    // Unknown loaders are reported at their key, serde would only point at the whole table
+    if let Some(enviroment) = document.get("enviroment").and_then(Item::as_table_like) {
+        for (key, _) in enviroment.iter() {
+            if key == "minecraft" {
+                continue;
+            }
+
+            if let Err(err) = key.parse::<Loader>() {
+                report.error(err, key_span(&document, "enviroment", key));
+            }
+        }
+    }
+
+    let manifest: Manifest = match toml_edit::de::from_str(&src) {
+        Ok(manifest) => manifest,
+        Err(err) => {
+            if report.problems == 0 {
+                report.error(err.message(), err.span());
+            }
+            return report.finish();
+        }
+    };
+
+    let enviroment = &manifest.enviroment;
+    let minecraft = &enviroment.minecraft;
+
+    let game_versions = client.get_game_versions().await?;
+    let known_minecraft = game_versions
+        .iter()
+        .any(|version| version.version == *minecraft);
+
+    if !known_minecraft {
+        report.error(
+            format!("Minecraft {minecraft} doesn't exist"),
+            value_span(&document, &["enviroment", "minecraft"]),
+        );
+    }
+
+    if known_minecraft {
+        let loader_client = loaders::Client::new()?;
+        let mut loaders: Vec<_> = enviroment.loaders.iter().collect();
+        loaders.sort();
+
+        for (loader, version) in loaders {
+            let span = value_span(&document, &["enviroment", &loader.to_string()]);
+
+            match loader_client.versions(loader, minecraft).await {
+                Ok(versions) if versions.iter().any(|known| known.version == *version) => {}
+                Ok(_) => report.error(
+                    format!("{loader} {version} doesn't exist for minecraft {minecraft}"),
+                    span,
+                ),
+                // Unreachable metadata services are a failure of the check, not of the pack
+                Err(err) if err.is::<reqwest::Error>() => return Err(err),
+                Err(err) => report.error(err, span),
+            }
+        }
+    }
+
+    let names: Vec<_> = ProjectType::ALL
+        .into_iter()
+        .flat_map(|project_type| manifest.section(project_type).keys().cloned())
+        .collect();
+
+    let projects = client.get_projects(&names).await?;
+    let mut lockfile = Lockfile::read(LOCKFILE)?;
+    lockfile.retarget(enviroment);
+    let mut stale = Vec::new();
+
+    for project_type in ProjectType::ALL {
+        let table = project_type.as_table();
+        let mut definitions: Vec<_> = manifest.section(project_type).iter().collect();
+        definitions.sort_by_key(|(name, _)| *name);
+
+        for (name, definition) in definitions {
+            let exists = projects
+                .iter()
+                .any(|project| project.id == *name || project.slug.eq_ignore_ascii_case(name));
+
+            if !exists {
+                report.error(
+                    format!("Project '{name}' doesn't exist on Modrinth"),
+                    key_span(&document, table, name),
+                );
+                lockfile.section_mut(project_type).remove(name);
+                continue;
+            }
+
+            let locked = lockfile
+                .section(project_type)
+                .get(name)
+                .is_some_and(|package| package.satisfies(definition, minecraft));
+
+            if !locked {
+                stale.push((project_type, name, definition));
+            }
+        }
+    }
+
+    let manifest = &manifest;
+
+    let resolved = try_join_all(stale.into_iter().map(
+        |(project_type, name, definition)| async move {
+            let versions = client
+                .get_project_versions(name, minecraft, &manifest.enviroment.loaders)
+                .await?;
+
+            anyhow::Ok((project_type, name, definition, versions))
+        },
+    ))
+    .await?;
+
+    let mut loaders: Vec<_> = enviroment.loaders.keys().map(Loader::to_string).collect();
+    loaders.sort();
+    let loaders = loaders.join(", ");
+
+    for (project_type, name, definition, versions) in resolved {
+        let table = project_type.as_table();
+
+        if versions.is_empty() {
+            lockfile.section_mut(project_type).remove(name);
+            report.error(
+                format!("{name} has no versions for minecraft {minecraft} on {loaders}"),
+                key_span(&document, table, name),
+            );
+            continue;
+        }
+
+        let strategy = manifest.strategy(definition);
+
+        match definition.version.select(versions, minecraft, &strategy) {
+            Some(version) => {
+                lockfile.section_mut(project_type).insert(
+                    name.clone(),
+                    Package::new(version, definition.side.clone())?,
+                );
+            }
+            None => {
+                lockfile.section_mut(project_type).remove(name);
+
+                let message = match definition.version {
+                    VersionReq::Latest => format!(
+                        "No version of {name} for minecraft {minecraft} matches the '{strategy}' strategy"
+                    ),
+                    _ => format!(
+                        "No version of {name} for minecraft {minecraft} on {loaders} matches '{}'",
+                        definition.version
+                    ),
+                };
+
+                report.error(
+                    message,
+                    value_span(&document, &[table, name, "version"])
+                        .or_else(|| value_span(&document, &[table, name])),
+                );
+            }
+        }
+    }
+
+    // Where every file of the pack ends up, to catch two of them writing the same path
+    let mut outputs: HashMap<PathBuf, (Side, String)> = HashMap::new();
+
+    for (project_type, name, package) in lockfile.packages() {
+        let path = package.path(project_type);
+
+        if let Some((side, other)) = outputs.get(&path) {
+            if shares(side, &package.side) {
+                report.error(
+                    format!("{name} and {other} both install {}", path.display()),
+                    key_span(&document, project_type.as_table(), name),
+                );
+            }
+            continue;
+        }
+
+        outputs.insert(path, (package.side.clone(), name.clone()));
+    }
+
+    let mut locations: Vec<_> = manifest.files.iter().collect();
+    locations.sort();
+
+    let mut matched: HashMap<PathBuf, Vec<_>> = HashMap::new();
+
+    for (location, patterns) in locations {
+        let side = match location {
+            FileLocation::Client => Side::Client,
+            FileLocation::Server => Side::Server,
+            FileLocation::Common => Side::Both,
+        };
+        let location_key = location.to_string();
+
+        for (index, pattern) in patterns.iter().enumerate() {
+            let span = document
+                .get("files")
+                .and_then(|files| files.get(&location_key))
+                .and_then(|patterns| patterns.get(index))
+                .and_then(Item::span);
+
+            let overrides = match location.expand(pattern) {
+                Ok(overrides) => overrides,
+                Err(err) => {
+                    report.error(format!("Invalid pattern '{pattern}': {err}"), span);
+                    continue;
+                }
+            };
+
+            if overrides.is_empty() {
+                report.error(format!("'{pattern}' doesn't match any files"), span.clone());
+            }
+
+            let mut reported = HashSet::new();
+
+            for entry in overrides {
+                // Client and server installs both get the common files, so overlaps are
+                // found by where files get installed rather than by override directory
+                let others: &mut Vec<(Side, &FileLocation, &str)> =
+                    matched.entry(entry.target.clone()).or_default();
+                let overlap = others
+                    .iter()
+                    .find(|(other_side, _, _)| shares(&side, other_side))
+                    .map(|(_, other_location, other)| (*other_location, *other));
+                others.push((side.clone(), location, pattern));
+
+                if let Some((other_location, other)) = overlap {
+                    if reported.insert((other_location, other)) {
+                        report.error(
+                            format!(
+                                "'{pattern}' overlaps with '{other}' in the {other_location} files, both install {}",
+                                entry.target.display()
+                            ),
+                            span.clone(),
+                        );
+                    }
+                    continue;
+                }
+
+                if let Some((package_side, name)) = outputs.get(&entry.target)
+                    && shares(&side, package_side)
+                {
+                    report.error(
+                        format!(
+                            "{} from '{pattern}' overwrites the file of {name}",
+                            entry.source.display()
+                        ),
+                        span.clone(),
+                    );
+                }
+            }
+        }
+    }
+
+    // Conflicts need every project resolved, they'd only add noise on top of the problems above
+    if report.problems == 0 {
+        lockfile.resolve(client, manifest).await?;
+
+        for conflict in find_conflicts(client, &lockfile).await? {
+            let project_type = ProjectType::ALL
+                .into_iter()
+                .find(|project_type| {
+                    manifest
+                        .section(*project_type)
+                        .contains_key(&conflict.project)
+                })
+                .unwrap_or(ProjectType::Mod);
+
+            report.error(
+                &conflict,
+                key_span(&document, project_type.as_table(), &conflict.project),
+            );
+        }
+    }
+
+    report.finish()
+}
@@ -1,25 +1,185 @@
-use std::fs;
+use std::{fmt::Display, fs, path::PathBuf, str::FromStr};
 
-use anyhow::Result;
+use anyhow::{Result, bail};
 use async_zip::base::write::ZipFileWriter;
+use futures_util::future::try_join_all;
 use tokio::fs::File;
 
-use crate::{manifest::Manifest, modrinth::Client};
+use crate::{
+    ProjectType,
+    conflicts::ensure_compatible,
+    curseforge,
+    install::install,
+    lockfile::{LOCKFILE, Lockfile},
+    manifest::{CurseForgeFile, FileLocation, Manifest, Side},
+    registry::Registry,
+};
 
-pub async fn export(client: &Client) -> Result<()> {
+#[derive(Clone, Copy)]
+pub enum ExportFormat {
+    Mrpack,
+    CurseForge,
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format {
+            "mrpack" | "modrinth" => Ok(Self::Mrpack),
+            "curseforge" => Ok(Self::CurseForge),
+            _ => Err(format!(
+                "Unknown format '{format}'. Supported formats are: mrpack, curseforge",
+            )),
+        }
+    }
+}
+
+impl Display for ExportFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Mrpack => write!(f, "mrpack"),
+            Self::CurseForge => write!(f, "curseforge"),
+        }
+    }
+}
+
+pub async fn export(
+    client: &impl Registry,
+    format: ExportFormat,
+    server: Option<PathBuf>,
+) -> Result<()> {
     let manifest: Manifest = toml_edit::de::from_slice(&fs::read("podzol.toml")?)?;
 
-    let mut writer = ZipFileWriter::with_tokio(
-        File::create(format!(
-            "{}-{}.mrpack",
-            manifest.pack.name, manifest.pack.version
-        ))
-        .await?,
-    );
+    let mut lockfile = Lockfile::read(LOCKFILE)?;
+    lockfile.resolve(client, &manifest).await?;
+    lockfile.write(LOCKFILE)?;
+
+    ensure_compatible(client, &lockfile).await?;
+
+    if let Some(server) = server {
+        let packages = lockfile
+            .packages()
+            .filter(|(_, _, package)| package.side != Side::Client)
+            .map(|(project_type, _, package)| (package.path(project_type), package))
+            .collect();
+
+        let overrides = manifest
+            .overrides()?
+            .into_iter()
+            .filter(|file| file.location != FileLocation::Client)
+            .collect();
+
+        return install(client, &server, packages, overrides).await;
+    }
+
+    match format {
+        ExportFormat::Mrpack => {
+            let mut writer = ZipFileWriter::with_tokio(
+                File::create(format!(
+                    "{}-{}.mrpack",
+                    manifest.pack.name, manifest.pack.version
+                ))
+                .await?,
+            );
+
+            manifest.build_mrpack(&lockfile, &mut writer).await?;
+
+            writer.close().await?;
+        }
+        ExportFormat::CurseForge => {
+            let files = curseforge_files(client, &manifest, &lockfile).await?;
 
-    manifest.build_mrpack(client, &mut writer).await?;
+            let mut writer = ZipFileWriter::with_tokio(
+                File::create(format!(
+                    "{}-{}.zip",
+                    manifest.pack.name, manifest.pack.version
+                ))
+                .await?,
+            );
 
-    writer.close().await?;
+            manifest.build_curseforge(files, &mut writer).await?;
+
+            writer.close().await?;
+        }
+    }
 
     Ok(())
 }
+
+/// Maps every client side package to a CurseForge file.
+///
+/// Explicit `curseforge` entries in the manifest win unless they were recorded for another
+/// version, everything else is downloaded and looked up by its fingerprint.
+async fn curseforge_files(
+    client: &impl Registry,
+    manifest: &Manifest,
+    lockfile: &Lockfile,
+) -> Result<Vec<(String, CurseForgeFile)>> {
+    let mut files = Vec::new();
+    let mut pending = Vec::new();
+
+    for project_type in ProjectType::ALL {
+        for (name, package) in lockfile.section(project_type) {
+            if package.side == Side::Server {
+                continue;
+            }
+
+            let mapped = manifest
+                .section(project_type)
+                .get(name)
+                .and_then(|definition| definition.curseforge.clone())
+                .filter(|file| {
+                    file.version
+                        .as_ref()
+                        .is_none_or(|version| *version == package.version_id)
+                });
+
+            match mapped {
+                Some(file) => files.push((name.clone(), file)),
+                None => pending.push((name, package)),
+            }
+        }
+    }
+
+    let fingerprints = try_join_all(pending.iter().map(|(_, package)| async {
+        let data = client.download(&package.url, &package.sha512).await?;
+        anyhow::Ok(curseforge::fingerprint(&data))
+    }))
+    .await?;
+
+    let matches = curseforge::Client::new()?
+        .get_fingerprint_matches(&fingerprints)
+        .await?;
+
+    let mut missing = Vec::new();
+
+    for ((name, _), fingerprint) in pending.into_iter().zip(fingerprints) {
+        match matches.get(&fingerprint) {
+            Some(file) => files.push((
+                name.clone(),
+                CurseForgeFile {
+                    project: file.mod_id,
+                    file: file.id,
+                    version: None,
+                },
+            )),
+            None => missing.push(name.as_str()),
+        }
+    }
+
+    if !missing.is_empty() {
+        bail!(
+            "Could not find the following projects on CurseForge:\n{}\nMap them manually with a `curseforge = {{ project = ..., file = ... }}` entry",
+            missing
+                .iter()
+                .map(|name| format!("  - {name}"))
+                .collect::<Vec<_>>()
+                .join("\n")
+        );
+    }
+
+    files.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    Ok(files)
+}
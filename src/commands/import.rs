@@ -0,0 +1,111 @@
+use anyhow::{Result, bail};
+use async_zip::base::read::mem::ZipFileReader;
+use std::{collections::HashMap, fs, path::Path};
+
+use super::init::scaffold;
+use crate::{
+    ProjectType,
+    install::sanitize,
+    lockfile::{LOCKFILE, Lockfile},
+    manifest::{FileLocation, Manifest},
+    modrinth::Project,
+    registry::Registry,
+};
+
+mod curseforge;
+mod mrpack;
+mod packwiz;
+
+enum Format {
+    Mrpack,
+    Packwiz,
+    CurseForge,
+}
+
+impl Format {
+    async fn detect(source: &Path) -> Result<Self> {
+        if source.is_dir() || source.ends_with("pack.toml") {
+            return Ok(Self::Packwiz);
+        }
+
+        let reader = ZipFileReader::new(fs::read(source)?).await?;
+
+        for entry in reader.file().entries() {
+            match entry.filename().as_str()? {
+                "modrinth.index.json" => return Ok(Self::Mrpack),
+                "manifest.json" => return Ok(Self::CurseForge),
+                _ => {}
+            }
+        }
+
+        bail!("{} is not a modpack podzol can import", source.display())
+    }
+}
+
+/// The result of converting a pack from another format
+struct Imported {
+    manifest: Manifest,
+    lockfile: Lockfile,
+    /// Files that couldn't be mapped back to a Modrinth project, with the reason why
+    unmapped: Vec<(String, String)>,
+}
+
+pub async fn import(client: &impl Registry, source: &Path, path: &Path) -> Result<()> {
+    if fs::exists(path.join("podzol.toml"))? {
+        bail!("{} already contains a podzol project", path.display());
+    }
+
+    fs::create_dir_all(path)?;
+
+    let Imported {
+        manifest,
+        lockfile,
+        unmapped,
+    } = match Format::detect(source).await? {
+        Format::Mrpack => mrpack::import(client, source, path).await?,
+        Format::Packwiz => packwiz::import(client, source, path).await?,
+        Format::CurseForge => curseforge::import(client, source, path).await?,
+    };
+
+    fs::write(
+        path.join("podzol.toml"),
+        toml_edit::ser::to_string_pretty(&manifest)?,
+    )?;
+    lockfile.write(path.join(LOCKFILE))?;
+
+    scaffold(path, &manifest.pack.name)?;
+
+    let imported = manifest.mods.len() + manifest.resource_packs.len() + manifest.shaders.len();
+    println!("Imported {} with {imported} project(s)", manifest.pack.name);
+
+    if !unmapped.is_empty() {
+        println!("\nThe following files could not be imported:");
+
+        for (file, reason) in unmapped {
+            println!("  - {file}: {reason}");
+        }
+    }
+
+    Ok(())
+}
+
+/// Picks the section for an imported file, by the directory it was installed into or else by
+/// the kind of project it belongs to
+fn project_type(path: &Path, project: &Project) -> ProjectType {
+    path.iter()
+        .next()
+        .and_then(|directory| {
+            ProjectType::ALL
+                .into_iter()
+                .find(|project_type| directory == project_type.as_directory())
+        })
+        .or_else(|| ProjectType::from_modrinth(&project.project_type))
+        .unwrap_or(ProjectType::Mod)
+}
+
+/// Includes every file extracted into the override directory of `location` in the pack
+fn include_overrides(files: &mut HashMap<FileLocation, Vec<String>>, location: &FileLocation) {
+    files
+        .entry(location.clone())
+        .or_insert_with(|| vec![format!("{}/**/*", location.as_ovveride().display())]);
+}
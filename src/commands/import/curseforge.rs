@@ -0,0 +1,332 @@
+use anyhow::{Result, anyhow};
+use async_zip::base::read::mem::ZipFileReader;
+use std::{collections::HashMap, fs, path::Path};
+
+use super::{Imported, include_overrides, sanitize};
+use crate::{
+    ProjectType,
+    constraint::VersionReq,
+    curseforge,
+    lockfile::{Lockfile, Package},
+    manifest::{CurseForgeFile, Definition, Enviroment, FileLocation, Manifest, Pack},
+    registry::Registry,
+};
+
+pub async fn import(client: &impl Registry, source: &Path, path: &Path) -> Result<Imported> {
+    let reader = ZipFileReader::new(fs::read(source)?).await?;
+
+    let index = reader
+        .file()
+        .entries()
+        .iter()
+        .position(|entry| {
+            entry
+                .filename()
+                .as_str()
+                .is_ok_and(|name| name == "manifest.json")
+        })
+        .ok_or_else(|| anyhow!("{} has no manifest.json", source.display()))?;
+
+    let mut data = Vec::new();
+    reader
+        .reader_with_entry(index)
+        .await?
+        .read_to_end_checked(&mut data)
+        .await?;
+    let cf_manifest: curseforge::Manifest = serde_json::from_slice(&data)?;
+
+    let mut files = HashMap::new();
+
+    for index in 0..reader.file().entries().len() {
+        let entry = &reader.file().entries()[index];
+        let filename = entry.filename().as_str()?.to_string();
+
+        if entry.dir()? {
+            continue;
+        }
+
+        let Ok(relative) = Path::new(&filename).strip_prefix(&cf_manifest.overrides) else {
+            continue;
+        };
+
+        let location = FileLocation::Common;
+        let target = path.join(location.as_ovveride()).join(sanitize(relative)?);
+
+        let mut data = Vec::new();
+        reader
+            .reader_with_entry(index)
+            .await?
+            .read_to_end_checked(&mut data)
+            .await?;
+
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(target, data)?;
+
+        include_overrides(&mut files, &location);
+    }
+
+    let mut manifest = Manifest {
+        pack: Pack {
+            name: cf_manifest.name,
+            version: cf_manifest.version,
+            description: None,
+            strategy: None,
+        },
+        enviroment: Enviroment {
+            minecraft: cf_manifest.minecraft.version,
+            loaders: cf_manifest
+                .minecraft
+                .mod_loaders
+                .iter()
+                .filter_map(curseforge::ModLoader::parse)
+                .collect(),
+        },
+        modrinth: None,
+        files,
+        mods: HashMap::new(),
+        resource_packs: HashMap::new(),
+        shaders: HashMap::new(),
+    };
+    let mut lockfile = Lockfile::default();
+    lockfile.retarget(&manifest.enviroment);
+    let mut unmapped = Vec::new();
+
+    let file_ids: Vec<_> = cf_manifest.files.iter().map(|file| file.file_id).collect();
+    let cf_files: HashMap<_, _> = curseforge::Client::new()?
+        .get_files(&file_ids)
+        .await?
+        .into_iter()
+        .map(|file| (file.id, file))
+        .collect();
+
+    let hashes: Vec<_> = cf_files
+        .values()
+        .filter_map(|file| file.sha1().map(str::to_string))
+        .collect();
+    let mut versions = client.get_versions_from_hashes(&hashes, "sha1").await?;
+
+    let project_ids: Vec<_> = versions
+        .values()
+        .map(|version| version.project_id.clone())
+        .collect();
+    let projects: HashMap<_, _> = client
+        .get_projects(&project_ids)
+        .await?
+        .into_iter()
+        .map(|project| (project.id.clone(), project))
+        .collect();
+
+    for file in cf_manifest.files {
+        let Some(cf_file) = cf_files.get(&file.file_id) else {
+            unmapped.push((
+                format!("project {} file {}", file.project_id, file.file_id),
+                "not found on CurseForge".to_string(),
+            ));
+            continue;
+        };
+
+        let Some(version) = cf_file.sha1().and_then(|hash| versions.remove(hash)) else {
+            unmapped.push((
+                cf_file.display_name.clone(),
+                "no Modrinth equivalent".to_string(),
+            ));
+            continue;
+        };
+
+        let Some(project) = projects.get(&version.project_id) else {
+            unmapped.push((
+                cf_file.display_name.clone(),
+                format!("project {} is not visible", version.project_id),
+            ));
+            continue;
+        };
+
+        let project_type =
+            ProjectType::from_modrinth(&project.project_type).unwrap_or(ProjectType::Mod);
+        let side = project.get_side();
+
+        manifest.section_mut(project_type).insert(
+            project.slug.clone(),
+            Definition {
+                version: VersionReq::Exact(version.version_number.clone()),
+                side: side.clone(),
+                dependency: false,
+                strategy: None,
+                curseforge: Some(CurseForgeFile {
+                    project: file.project_id,
+                    file: file.file_id,
+                    version: Some(version.id.clone()),
+                }),
+            },
+        );
+        lockfile
+            .section_mut(project_type)
+            .insert(project.slug.clone(), Package::new(version, side)?);
+    }
+
+    Ok(Imported {
+        manifest,
+        lockfile,
+        unmapped,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use async_zip::{Compression, ZipEntryBuilder, base::write::ZipFileWriter};
+    use std::{
+        env,
+        io::{Read, Write},
+        net::TcpListener,
+        path::PathBuf,
+        thread,
+    };
+
+    use super::*;
+    use crate::registry::fake::{Fake, version};
+
+    /// Answers a single `/mods/files` request the way the CurseForge API would
+    fn serve(files: serde_json::Value) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0; 4096];
+
+            // Headers and the small JSON body are read until the body is complete
+            loop {
+                let read = stream.read(&mut buf).unwrap();
+                assert_ne!(read, 0, "the request ended early");
+                request.extend_from_slice(&buf[..read]);
+
+                let text = String::from_utf8_lossy(&request);
+                if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                    let length = head
+                        .lines()
+                        .find_map(|line| {
+                            line.to_lowercase()
+                                .strip_prefix("content-length: ")
+                                .map(str::to_string)
+                        })
+                        .and_then(|length| length.parse().ok())
+                        .unwrap_or(0);
+
+                    if body.len() >= length {
+                        assert!(head.starts_with("POST /mods/files "));
+                        break;
+                    }
+                }
+            }
+
+            let body = serde_json::json!({ "data": files }).to_string();
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            )
+            .unwrap();
+        });
+
+        url
+    }
+
+    async fn pack(dir: &Path) -> PathBuf {
+        let manifest = serde_json::json!({
+            "minecraft": {
+                "version": "1.21.1",
+                "modLoaders": [{ "id": "fabric-0.16.10", "primary": true }]
+            },
+            "manifestType": "minecraftModpack",
+            "manifestVersion": 1,
+            "name": "test",
+            "version": "1.0.0",
+            "files": [
+                { "projectID": 1, "fileID": 10, "required": true },
+                { "projectID": 2, "fileID": 20, "required": true },
+                { "projectID": 3, "fileID": 30, "required": true }
+            ],
+            "overrides": "overrides"
+        });
+
+        let mut writer = ZipFileWriter::new(Vec::new());
+        for (name, data) in [
+            ("manifest.json", manifest.to_string()),
+            ("overrides/config/sodium.json", "{}".to_string()),
+        ] {
+            let entry = ZipEntryBuilder::new(name.into(), Compression::Stored);
+            writer
+                .write_entry_whole(entry, data.as_bytes())
+                .await
+                .unwrap();
+        }
+
+        let source = dir.join("pack.zip");
+        fs::write(&source, writer.close().await.unwrap()).unwrap();
+        source
+    }
+
+    #[tokio::test]
+    async fn maps_files_and_reports_the_rest() {
+        let dir = env::temp_dir().join(format!("podzol-curseforge-import-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let source = pack(&dir).await;
+
+        let url = serve(serde_json::json!([
+            {
+                "id": 10, "modId": 1, "displayName": "Sodium", "fileFingerprint": 0,
+                "hashes": [{ "value": "s1-sha1", "algo": 1 }]
+            },
+            {
+                "id": 20, "modId": 2, "displayName": "Private Mod", "fileFingerprint": 0,
+                "hashes": [{ "value": "unknown", "algo": 1 }]
+            }
+        ]));
+
+        // SAFETY: no other test reads the CurseForge variables
+        unsafe {
+            env::set_var("PODZOL_CURSEFORGE_URL", url);
+            env::set_var("CURSEFORGE_API_KEY", "test");
+        }
+
+        let client = Fake::default()
+            .project("AAA", "sodium")
+            .version(version("s1", "AAA", "0.6.5", 0));
+        let imported = import(&client, &source, &dir.join("project"))
+            .await
+            .unwrap();
+
+        let sodium = &imported.manifest.mods["sodium"];
+        let pin = sodium.curseforge.as_ref().unwrap();
+        assert_eq!(
+            (pin.project, pin.file, pin.version.as_deref()),
+            (1, 10, Some("s1"))
+        );
+        assert_eq!(imported.lockfile.mods["sodium"].version_id, "s1");
+
+        assert_eq!(
+            imported.unmapped,
+            [
+                (
+                    "Private Mod".to_string(),
+                    "no Modrinth equivalent".to_string()
+                ),
+                (
+                    "project 3 file 30".to_string(),
+                    "not found on CurseForge".to_string()
+                ),
+            ]
+        );
+
+        assert!(fs::exists(dir.join("project/overrides/config/sodium.json")).unwrap());
+        assert_eq!(
+            imported.manifest.files[&FileLocation::Common],
+            ["overrides/**/*"]
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,168 @@
+use anyhow::{Result, anyhow};
+use async_zip::base::read::mem::ZipFileReader;
+use std::{collections::HashMap, fs, path::Path};
+
+use super::{Imported, include_overrides, project_type, sanitize};
+use crate::{
+    constraint::VersionReq,
+    lockfile::{Lockfile, Package},
+    manifest::{Definition, Enviroment, FileLocation, Loader, Manifest, Pack, Side},
+    mrpack::Metadata,
+    registry::Registry,
+};
+
+pub async fn import(client: &impl Registry, source: &Path, path: &Path) -> Result<Imported> {
+    let reader = ZipFileReader::new(fs::read(source)?).await?;
+
+    let mut metadata: Option<Metadata> = None;
+    let mut files: HashMap<FileLocation, Vec<String>> = HashMap::new();
+
+    for index in 0..reader.file().entries().len() {
+        let entry = &reader.file().entries()[index];
+        let filename = entry.filename().as_str()?.to_string();
+
+        if entry.dir()? {
+            continue;
+        }
+
+        let mut data = Vec::new();
+        reader
+            .reader_with_entry(index)
+            .await?
+            .read_to_end_checked(&mut data)
+            .await?;
+
+        if filename == "modrinth.index.json" {
+            metadata = Some(serde_json::from_slice(&data)?);
+            continue;
+        }
+
+        let overrides = [
+            FileLocation::Common,
+            FileLocation::Client,
+            FileLocation::Server,
+        ]
+        .into_iter()
+        .find_map(|location| {
+            Path::new(&filename)
+                .strip_prefix(location.as_ovveride())
+                .ok()
+                .map(|relative| (location, relative))
+        });
+
+        let Some((location, relative)) = overrides else {
+            continue;
+        };
+
+        let target = path.join(location.as_ovveride()).join(sanitize(relative)?);
+
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(target, data)?;
+
+        include_overrides(&mut files, &location);
+    }
+
+    let metadata =
+        metadata.ok_or_else(|| anyhow!("{} has no modrinth.index.json", source.display()))?;
+
+    let mut minecraft = None;
+    let mut loaders = HashMap::new();
+
+    for (dependency, version) in metadata.dependencies {
+        if dependency == "minecraft" {
+            minecraft = Some(version);
+        } else if let Some(loader) = Loader::from_mrpack(&dependency) {
+            loaders.insert(loader, version);
+        }
+    }
+
+    let mut manifest = Manifest {
+        pack: Pack {
+            name: metadata.name,
+            version: metadata.version_id,
+            description: metadata.summary,
+            strategy: None,
+        },
+        enviroment: Enviroment {
+            minecraft: minecraft.ok_or_else(|| anyhow!("The pack has no minecraft dependency"))?,
+            loaders,
+        },
+        modrinth: None,
+        files,
+        mods: HashMap::new(),
+        resource_packs: HashMap::new(),
+        shaders: HashMap::new(),
+    };
+    let mut lockfile = Lockfile::default();
+    lockfile.retarget(&manifest.enviroment);
+    let mut unmapped = Vec::new();
+
+    let hashes: Vec<_> = metadata
+        .files
+        .iter()
+        .filter_map(|file| file.hashes.get("sha1").cloned())
+        .collect();
+
+    let mut versions = client.get_versions_from_hashes(&hashes, "sha1").await?;
+
+    let project_ids: Vec<_> = versions
+        .values()
+        .map(|version| version.project_id.clone())
+        .collect();
+    let projects: HashMap<_, _> = client
+        .get_projects(&project_ids)
+        .await?
+        .into_iter()
+        .map(|project| (project.id.clone(), project))
+        .collect();
+
+    for file in metadata.files {
+        let path = file.path.display().to_string();
+
+        let Some(version) = file
+            .hashes
+            .get("sha1")
+            .and_then(|hash| versions.remove(hash))
+        else {
+            unmapped.push((path, "not found on Modrinth".to_string()));
+            continue;
+        };
+
+        let Some(project) = projects.get(&version.project_id) else {
+            unmapped.push((
+                path,
+                format!("project {} is not visible", version.project_id),
+            ));
+            continue;
+        };
+
+        let project_type = project_type(&file.path, project);
+
+        let side = match &file.env {
+            Some(env) => Side::from(env),
+            None => project.get_side(),
+        };
+
+        manifest.section_mut(project_type).insert(
+            project.slug.clone(),
+            Definition {
+                version: VersionReq::Exact(version.version_number.clone()),
+                side: side.clone(),
+                dependency: false,
+                strategy: None,
+                curseforge: None,
+            },
+        );
+        lockfile
+            .section_mut(project_type)
+            .insert(project.slug.clone(), Package::new(version, side)?);
+    }
+
+    Ok(Imported {
+        manifest,
+        lockfile,
+        unmapped,
+    })
+}
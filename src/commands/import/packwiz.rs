@@ -0,0 +1,210 @@
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, de::IgnoredAny};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use super::{Imported, include_overrides, project_type, sanitize};
+use crate::{
+    constraint::VersionReq,
+    lockfile::{Lockfile, Package},
+    manifest::{Definition, Enviroment, FileLocation, Loader, Manifest, Pack, Side},
+    registry::Registry,
+};
+
+#[derive(Debug, Deserialize)]
+struct PackToml {
+    name: String,
+    version: Option<String>,
+    description: Option<String>,
+    index: IndexRef,
+    versions: HashMap<String, String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct IndexRef {
+    file: PathBuf,
+}
+
+#[derive(Debug, Deserialize)]
+struct Index {
+    #[serde(default)]
+    files: Vec<IndexFile>,
+}
+
+#[derive(Debug, Deserialize)]
+struct IndexFile {
+    file: PathBuf,
+    #[serde(default)]
+    metafile: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct Metafile {
+    #[serde(default = "default_side")]
+    side: Side,
+    #[serde(default)]
+    update: Update,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Update {
+    modrinth: Option<ModrinthUpdate>,
+    curseforge: Option<IgnoredAny>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct ModrinthUpdate {
+    version: String,
+}
+
+const fn default_side() -> Side {
+    Side::Both
+}
+
+pub async fn import(client: &impl Registry, source: &Path, path: &Path) -> Result<Imported> {
+    let pack_toml = if source.is_dir() {
+        source.join("pack.toml")
+    } else {
+        source.to_path_buf()
+    };
+    let root = pack_toml.parent().unwrap_or(Path::new("."));
+
+    let pack: PackToml = toml_edit::de::from_slice(&fs::read(&pack_toml)?)?;
+    let index: Index = toml_edit::de::from_slice(&fs::read(root.join(&pack.index.file))?)?;
+
+    let mut minecraft = None;
+    let mut loaders = HashMap::new();
+
+    for (component, version) in pack.versions {
+        if component == "minecraft" {
+            minecraft = Some(version);
+        } else if let Ok(loader) = component.parse::<Loader>() {
+            loaders.insert(loader, version);
+        }
+    }
+
+    let mut manifest = Manifest {
+        pack: Pack {
+            name: pack.name,
+            version: pack.version.unwrap_or_else(|| "0.1.0".to_string()),
+            description: pack.description,
+            strategy: None,
+        },
+        enviroment: Enviroment {
+            minecraft: minecraft.ok_or_else(|| anyhow!("pack.toml has no minecraft version"))?,
+            loaders,
+        },
+        modrinth: None,
+        files: HashMap::new(),
+        mods: HashMap::new(),
+        resource_packs: HashMap::new(),
+        shaders: HashMap::new(),
+    };
+    let mut lockfile = Lockfile::default();
+    lockfile.retarget(&manifest.enviroment);
+    let mut unmapped = Vec::new();
+
+    let mut metafiles = Vec::new();
+
+    for file in index.files {
+        let source = root.join(&file.file);
+
+        if file.metafile {
+            let metafile: Metafile = toml_edit::de::from_slice(&fs::read(&source)?)?;
+            metafiles.push((file.file, metafile));
+            continue;
+        }
+
+        let location = FileLocation::Common;
+        let target = path
+            .join(location.as_ovveride())
+            .join(sanitize(&file.file)?);
+
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(source, target)?;
+
+        include_overrides(&mut manifest.files, &location);
+    }
+
+    let version_ids: Vec<_> = metafiles
+        .iter()
+        .filter_map(|(_, metafile)| metafile.update.modrinth.as_ref())
+        .map(|modrinth| modrinth.version.clone())
+        .collect();
+
+    let mut versions: HashMap<_, _> = client
+        .get_versions(&version_ids)
+        .await?
+        .into_iter()
+        .map(|version| (version.id.clone(), version))
+        .collect();
+
+    let project_ids: Vec<_> = versions
+        .values()
+        .map(|version| version.project_id.clone())
+        .collect();
+    let projects: HashMap<_, _> = client
+        .get_projects(&project_ids)
+        .await?
+        .into_iter()
+        .map(|project| (project.id.clone(), project))
+        .collect();
+
+    for (file, metafile) in metafiles {
+        let name = file.display().to_string();
+
+        let Some(modrinth) = metafile.update.modrinth else {
+            let reason = if metafile.update.curseforge.is_some() {
+                "only available on CurseForge"
+            } else {
+                "only available from a URL"
+            };
+            unmapped.push((name, reason.to_string()));
+            continue;
+        };
+
+        let Some(version) = versions.remove(&modrinth.version) else {
+            unmapped.push((
+                name,
+                format!("version {} not found on Modrinth", modrinth.version),
+            ));
+            continue;
+        };
+
+        let Some(project) = projects.get(&version.project_id) else {
+            unmapped.push((
+                name,
+                format!("project {} is not visible", version.project_id),
+            ));
+            continue;
+        };
+
+        let project_type = project_type(&file, project);
+
+        manifest.section_mut(project_type).insert(
+            project.slug.clone(),
+            Definition {
+                version: VersionReq::Exact(version.version_number.clone()),
+                side: metafile.side.clone(),
+                dependency: false,
+                strategy: None,
+                curseforge: None,
+            },
+        );
+        lockfile
+            .section_mut(project_type)
+            .insert(project.slug.clone(), Package::new(version, metafile.side)?);
+    }
+
+    Ok(Imported {
+        manifest,
+        lockfile,
+        unmapped,
+    })
+}
@@ -3,8 +3,10 @@ use inquire::{Select, Text};
 use std::{collections::HashMap, env::current_dir, fs, path::Path};
 
 use crate::{
-    manifest::{self, Manifest},
-    modrinth::{Client, VersionType},
+    loaders,
+    manifest::{self, Loader, Manifest},
+    modrinth::VersionType,
+    registry::Registry,
 };
 
 fn name_from_path(path: &Path) -> &str {
@@ -13,7 +15,7 @@ fn name_from_path(path: &Path) -> &str {
         .unwrap_or("pack")
 }
 
-pub async fn init_interactive(client: &Client) -> Result<()> {
+pub async fn init_interactive(client: &impl Registry, loader: Option<Loader>) -> Result<()> {
     let pwd = current_dir().expect("Failed to fetch current dir");
 
     let name = Text::new("Name")
@@ -30,15 +32,29 @@ pub async fn init_interactive(client: &Client) -> Result<()> {
 
     let game_version = Select::new("Game version", versions).prompt()?;
 
-    init(client, &pwd, version, Some(game_version), Some(name)).await
+    let loader = match loader {
+        Some(loader) => Some(loader),
+        None => Select::new("Loader (esc for none)", Loader::ALL.to_vec()).prompt_skippable()?,
+    };
+
+    init(
+        client,
+        &pwd,
+        version,
+        Some(game_version),
+        Some(name),
+        loader,
+    )
+    .await
 }
 
 pub async fn init(
-    client: &Client,
+    client: &impl Registry,
     path: &Path,
     version: String,
     game_version: Option<String>,
     name: Option<String>,
+    loader: Option<Loader>,
 ) -> Result<()> {
     let name = if let Some(name) = name {
         name
@@ -60,16 +76,29 @@ pub async fn init(
         latest_version.version
     };
 
+    let mut loaders = HashMap::new();
+
+    if let Some(loader) = loader {
+        let version = loaders::Client::new()?
+            .latest(&loader, &minecraft_version)
+            .await?;
+
+        println!("Using {loader} {version}");
+        loaders.insert(loader, version);
+    }
+
     let manifest = Manifest {
         pack: manifest::Pack {
             name,
             version,
             description: None,
+            strategy: None,
         },
         enviroment: manifest::Enviroment {
             minecraft: minecraft_version,
-            loaders: HashMap::new(),
+            loaders,
         },
+        modrinth: None,
         files: HashMap::new(),
         mods: HashMap::new(),
         resource_packs: HashMap::new(),
@@ -81,11 +110,24 @@ pub async fn init(
         &toml_edit::ser::to_string_pretty(&manifest)?,
     )?;
 
+    scaffold(path, &manifest.pack.name)
+}
+
+/// Writes the files every podzol project starts with next to its manifest
+pub fn scaffold(path: &Path, name: &str) -> Result<()> {
+    // Only the pack's own CurseForge export is ignored, zipped datapacks and resource packs
+    // in the override directories have to stay tracked
+    let mut pattern = String::new();
+    for c in name.chars() {
+        if matches!(c, '*' | '?' | '[' | '\\') {
+            pattern.push('\\');
+        }
+        pattern.push(c);
+    }
+
     fs::write(
         path.join(".gitignore"),
-        r#"# The exported modpack
-*.mrpack
-"#,
+        format!("# The exported modpack\n*.mrpack\n/{pattern}-*.zip\n"),
     )?;
 
     if !fs::exists(path.join(".git"))? {
@@ -0,0 +1,122 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::{fs, path::Path};
+
+use crate::{
+    install,
+    lockfile::{LOCKFILE, Lockfile},
+    manifest::{FileLocation, Loader, Manifest, Side},
+    registry::Registry,
+};
+
+/// The `mmc-pack.json` Prism Launcher and MultiMC describe an instance's components with
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct MmcPack {
+    components: Vec<Component>,
+    format_version: u32,
+}
+
+#[derive(Debug, Serialize)]
+struct Component {
+    uid: &'static str,
+    version: String,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    important: bool,
+}
+
+pub async fn install(client: &impl Registry, instance: &Path) -> Result<()> {
+    let manifest: Manifest = toml_edit::de::from_slice(&fs::read("podzol.toml")?)?;
+
+    let mut lockfile = Lockfile::read(LOCKFILE)?;
+    lockfile.resolve(client, &manifest).await?;
+    lockfile.write(LOCKFILE)?;
+
+    fs::create_dir_all(instance)?;
+
+    write_instance_cfg(instance, &manifest.pack.name)?;
+    fs::write(
+        instance.join("mmc-pack.json"),
+        serde_json::to_vec_pretty(&mmc_pack(&manifest))?,
+    )?;
+
+    // Older instances use `minecraft` instead of `.minecraft`
+    let game_dir = if fs::exists(instance.join("minecraft"))? {
+        instance.join("minecraft")
+    } else {
+        instance.join(".minecraft")
+    };
+
+    let packages = lockfile
+        .packages()
+        .filter(|(_, _, package)| package.side != Side::Server)
+        .map(|(project_type, _, package)| (package.path(project_type), package))
+        .collect();
+
+    let overrides = manifest
+        .overrides()?
+        .into_iter()
+        .filter(|file| file.location != FileLocation::Server)
+        .collect();
+
+    install::install(client, &game_dir, packages, overrides).await
+}
+
+fn mmc_pack(manifest: &Manifest) -> MmcPack {
+    let minecraft = &manifest.enviroment.minecraft;
+
+    let mut components = vec![Component {
+        uid: "net.minecraft",
+        version: minecraft.clone(),
+        important: true,
+    }];
+
+    let mut loaders: Vec<_> = manifest.enviroment.loaders.iter().collect();
+    loaders.sort();
+
+    for (loader, version) in loaders {
+        // Fabric and Quilt are loaded on top of Fabric's intermediary mappings
+        if matches!(loader, Loader::Fabric | Loader::Quilt) {
+            components.push(Component {
+                uid: "net.fabricmc.intermediary",
+                version: minecraft.clone(),
+                important: false,
+            });
+        }
+
+        components.push(Component {
+            uid: loader.as_component(),
+            version: version.clone(),
+            important: false,
+        });
+    }
+
+    MmcPack {
+        components,
+        format_version: 1,
+    }
+}
+
+/// Writes the instance type and name while keeping every other setting the launcher stored
+fn write_instance_cfg(instance: &Path, name: &str) -> Result<()> {
+    let path = instance.join("instance.cfg");
+
+    let existing = if fs::exists(&path)? {
+        fs::read_to_string(&path)?
+    } else {
+        String::new()
+    };
+
+    let mut cfg = format!("InstanceType=OneSix\nname={name}\n");
+
+    for line in existing.lines() {
+        if !line.starts_with("InstanceType=") && !line.starts_with("name=") {
+            cfg.push_str(line);
+            cfg.push('\n');
+        }
+    }
+
+    fs::write(path, cfg)?;
+
+    Ok(())
+}
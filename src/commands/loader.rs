@@ -0,0 +1,106 @@
+use anyhow::{Result, bail};
+use std::fs;
+use toml_edit::{DocumentMut, value};
+
+use crate::{
+    LoaderCommand,
+    loaders::{Client, numeric_key},
+    manifest::Manifest,
+};
+
+pub async fn loader(command: LoaderCommand) -> Result<()> {
+    let manifest_src = fs::read_to_string("podzol.toml")?;
+    let mut document: DocumentMut = manifest_src.parse()?;
+    let manifest: Manifest = toml_edit::de::from_document(document.clone())?;
+    let minecraft = &manifest.enviroment.minecraft;
+
+    let mut loaders: Vec<_> = manifest.enviroment.loaders.iter().collect();
+    loaders.sort();
+
+    let client = Client::new()?;
+
+    match command {
+        LoaderCommand::Show => {
+            if loaders.is_empty() {
+                println!("No loader configured");
+            }
+
+            for (loader, current) in loaders {
+                let latest = client.latest(loader, minecraft).await?;
+
+                if numeric_key(&latest) > numeric_key(current) {
+                    println!("{loader} {current} ({latest} available)");
+                } else {
+                    println!("{loader} {current}");
+                }
+            }
+        }
+        LoaderCommand::List { loader } => {
+            let loader = match (loader, loaders.as_slice()) {
+                (Some(loader), _) => loader,
+                (None, [(loader, _)]) => (*loader).clone(),
+                (None, []) => bail!("No loader configured, pass the one to list"),
+                (None, _) => bail!("The pack has multiple loaders, pass the one to list"),
+            };
+
+            let current = manifest.enviroment.loaders.get(&loader);
+
+            for version in client.versions(&loader, minecraft).await? {
+                let mut notes = Vec::new();
+                if !version.stable {
+                    notes.push("unstable");
+                }
+                if current == Some(&version.version) {
+                    notes.push("current");
+                }
+
+                if notes.is_empty() {
+                    println!("{}", version.version);
+                } else {
+                    println!("{} ({})", version.version, notes.join(", "));
+                }
+            }
+        }
+        LoaderCommand::Set { loader, version } => {
+            let supported = client
+                .versions(&loader, minecraft)
+                .await?
+                .iter()
+                .any(|candidate| candidate.version == version);
+
+            if !supported {
+                bail!("{loader} {version} doesn't support minecraft {minecraft}");
+            }
+
+            document["enviroment"][loader.to_string()] = value(&version);
+            fs::write("podzol.toml", document.to_string())?;
+
+            println!("Set {loader} to {version}");
+        }
+        LoaderCommand::Upgrade => {
+            let mut upgraded = 0;
+
+            for (loader, current) in loaders {
+                let latest = client.latest(loader, minecraft).await?;
+
+                // Packs deliberately on a newer unstable build are left alone
+                if numeric_key(&latest) <= numeric_key(current) {
+                    continue;
+                }
+
+                document["enviroment"][loader.to_string()] = value(&latest);
+                println!("{loader}: {current} → {latest}");
+                upgraded += 1;
+            }
+
+            if upgraded == 0 {
+                println!("Every loader is up to date");
+                return Ok(());
+            }
+
+            fs::write("podzol.toml", document.to_string())?;
+        }
+    }
+
+    Ok(())
+}
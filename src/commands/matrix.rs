@@ -0,0 +1,123 @@
+use anyhow::Result;
+use futures_util::future::try_join_all;
+use std::{cmp::Reverse, fs};
+
+use crate::{ProjectType, manifest::Manifest, modrinth::VersionType, registry::Registry};
+
+/// Whether a project has a version for a given minecraft version
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Availability {
+    Available,
+    /// Only versions the project's strategy doesn't allow exist
+    PreRelease,
+    Missing,
+}
+
+impl Availability {
+    const fn symbol(&self) -> &'static str {
+        match self {
+            Self::Available => "✓",
+            Self::PreRelease => "β",
+            Self::Missing => "✗",
+        }
+    }
+}
+
+pub async fn matrix(client: &impl Registry, snapshots: bool, limit: usize) -> Result<()> {
+    let manifest: Manifest = toml_edit::de::from_slice(&fs::read("podzol.toml")?)?;
+    let manifest = &manifest;
+    let enviroment = &manifest.enviroment;
+
+    let mut game_versions: Vec<_> = client
+        .get_game_versions()
+        .await?
+        .into_iter()
+        .filter(|version| snapshots || version.version_type == VersionType::Release)
+        .collect();
+    game_versions.sort_by_key(|version| Reverse(version.date));
+    game_versions.truncate(limit);
+
+    let game_versions: Vec<_> = game_versions
+        .into_iter()
+        .map(|version| version.version)
+        .collect();
+
+    let candidates = ProjectType::ALL.into_iter().flat_map(|project_type| {
+        let mut definitions: Vec<_> = manifest.section(project_type).iter().collect();
+        definitions.sort_by_key(|(name, _)| *name);
+        definitions
+    });
+
+    let rows = try_join_all(candidates.map(|(name, definition)| {
+        let game_versions = &game_versions;
+
+        async move {
+            let versions = client
+                .get_all_project_versions(name, &enviroment.loaders)
+                .await?;
+            let strategy = manifest.strategy(definition);
+
+            let availability: Vec<_> = game_versions
+                .iter()
+                .map(|game_version| {
+                    let mut compatible = versions
+                        .iter()
+                        .filter(|version| version.game_versions.contains(game_version))
+                        .peekable();
+
+                    if compatible.peek().is_none() {
+                        Availability::Missing
+                    } else if compatible.any(|version| strategy.allows(version)) {
+                        Availability::Available
+                    } else {
+                        Availability::PreRelease
+                    }
+                })
+                .collect();
+
+            anyhow::Ok((name, availability))
+        }
+    }))
+    .await?;
+
+    if rows.is_empty() {
+        println!("The pack has no projects");
+        return Ok(());
+    }
+
+    let name_width = rows.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+
+    print!("{:name_width$}", "");
+    for game_version in &game_versions {
+        print!("  {game_version}");
+    }
+    println!();
+
+    for (name, availability) in &rows {
+        let mut line = format!("{name:name_width$}");
+        for (game_version, availability) in game_versions.iter().zip(availability) {
+            line += &format!(
+                "  {:^width$}",
+                availability.symbol(),
+                width = game_version.len()
+            );
+        }
+        println!("{}", line.trim_end());
+    }
+
+    println!("\n✓ available, β pre-releases only, ✗ missing\n");
+
+    let best = game_versions.iter().enumerate().find(|(index, _)| {
+        rows.iter()
+            .all(|(_, availability)| availability[*index] == Availability::Available)
+    });
+
+    match best {
+        Some((_, game_version)) => {
+            println!("Newest version every project supports: {game_version}");
+        }
+        None => println!("None of these versions is supported by every project"),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,215 @@
+use anyhow::{Result, bail};
+use futures_util::future::try_join_all;
+use inquire::Confirm;
+use std::fs;
+use toml_edit::{DocumentMut, value};
+
+use crate::{
+    ProjectType,
+    conflicts::ensure_compatible,
+    constraint::VersionReq,
+    loaders,
+    lockfile::{LOCKFILE, Lockfile, Package},
+    manifest::{Enviroment, Manifest, Strategy},
+    modrinth::{Version, VersionType},
+    registry::Registry,
+};
+
+/// How a project carries over to the new minecraft version
+enum Migration {
+    /// Still satisfies its requirement
+    Available(Version),
+    /// Only a version outside of its requirement exists, it gets pinned to it
+    Pinned(Version),
+    /// Only pre-releases the project's strategy doesn't allow exist
+    PreRelease(Version),
+    Missing,
+}
+
+pub async fn migrate(client: &impl Registry, minecraft: String, yes: bool) -> Result<()> {
+    let manifest_src = fs::read_to_string("podzol.toml")?;
+    let mut document: DocumentMut = manifest_src.parse()?;
+    let manifest: Manifest = toml_edit::de::from_document(document.clone())?;
+    let mut lockfile = Lockfile::read(LOCKFILE)?;
+
+    let enviroment = &manifest.enviroment;
+
+    if enviroment.minecraft == minecraft {
+        bail!("The pack already targets minecraft {minecraft}");
+    }
+
+    let loader_client = loaders::Client::new()?;
+    let mut loaders: Vec<_> = enviroment.loaders.iter().collect();
+    loaders.sort();
+
+    let loaders = try_join_all(loaders.into_iter().map(|(loader, current)| {
+        let loader_client = &loader_client;
+        let minecraft = &minecraft;
+
+        async move {
+            let version = loader_client.latest(loader, minecraft).await?;
+            anyhow::Ok((loader, current, version))
+        }
+    }))
+    .await?;
+
+    let manifest = &manifest;
+    let target = &minecraft;
+
+    let candidates = ProjectType::ALL.into_iter().flat_map(|project_type| {
+        let mut definitions: Vec<_> = manifest.section(project_type).iter().collect();
+        definitions.sort_by_key(|(name, _)| *name);
+        definitions
+            .into_iter()
+            .map(move |(name, definition)| (project_type, name, definition))
+    });
+
+    let results = try_join_all(
+        candidates.map(|(project_type, name, definition)| async move {
+            let versions = client
+                .get_project_versions(name, target, &enviroment.loaders)
+                .await?;
+
+            let strategy = manifest.strategy(definition);
+
+            let migration = if versions.is_empty() {
+                Migration::Missing
+            } else if let Some(version) =
+                definition
+                    .version
+                    .select(versions.clone(), target, &strategy)
+            {
+                Migration::Available(version)
+            } else if let Some(version) = strategy.select(versions.clone()) {
+                Migration::Pinned(version)
+            } else {
+                Strategy::Alpha
+                    .select(versions)
+                    .map_or(Migration::Missing, Migration::PreRelease)
+            };
+
+            anyhow::Ok((project_type, name, definition, migration))
+        }),
+    )
+    .await?;
+
+    println!("Migrating from {} to {minecraft}", enviroment.minecraft);
+
+    for (loader, current, version) in &loaders {
+        println!("  {loader}: {current} → {version}");
+    }
+
+    let mut missing = 0;
+
+    for (project_type, name, definition, migration) in &results {
+        let current = match lockfile.section(*project_type).get(*name) {
+            Some(package) => package.version.clone(),
+            None => definition.version.to_string(),
+        };
+
+        match migration {
+            Migration::Available(version) => {
+                println!("  {name}: {current} → {}", version.version_number)
+            }
+            Migration::Pinned(version) if definition.version.is_exact() => {
+                println!("  {name}: {current} → {}", version.version_number)
+            }
+            Migration::Pinned(version) => println!(
+                "  {name}: {current} → {} (pinned, '{}' no longer matches)",
+                version.version_number, definition.version
+            ),
+            Migration::PreRelease(version) => println!(
+                "  {name}: {current} → {} (only a {} is available)",
+                version.version_number, version.version_type
+            ),
+            Migration::Missing => {
+                println!("  {name}: not available for minecraft {minecraft}");
+                missing += 1;
+            }
+        }
+    }
+
+    // Dropping projects is left to `podzol remove` so nothing disappears from the pack silently
+    if missing > 0 {
+        bail!(
+            "{missing} project(s) aren't available for minecraft {minecraft}, remove them with `podzol remove` before migrating"
+        );
+    }
+
+    let question = format!("Migrate the pack to minecraft {minecraft}?");
+
+    if !yes && !Confirm::new(&question).with_default(false).prompt()? {
+        return Ok(());
+    }
+
+    document["enviroment"]["minecraft"] = value(&minecraft);
+
+    for (loader, _, version) in &loaders {
+        document["enviroment"][loader.to_string()] = value(version);
+    }
+
+    lockfile.retarget(&Enviroment {
+        minecraft: minecraft.clone(),
+        loaders: loaders
+            .iter()
+            .map(|(loader, _, version)| ((*loader).clone(), version.clone()))
+            .collect(),
+    });
+
+    for (project_type, name, definition, migration) in results {
+        let table = project_type.as_table();
+
+        let version = match migration {
+            Migration::Available(version) => {
+                if definition.version.is_exact() {
+                    document[table][name]["version"] = value(&version.version_number);
+                }
+                version
+            }
+            Migration::Pinned(version) => {
+                if !matches!(definition.version, VersionReq::Latest) {
+                    document[table][name]["version"] = value(&version.version_number);
+                }
+                version
+            }
+            Migration::PreRelease(version) => {
+                let strategy = match version.version_type {
+                    VersionType::Beta => Strategy::Beta,
+                    _ => Strategy::Alpha,
+                };
+
+                if !matches!(definition.version, VersionReq::Latest) {
+                    document[table][name]["version"] = value(&version.version_number);
+                }
+                document[table][name]["strategy"] = value(strategy.to_string());
+                if let Some(definition) = document[table][name].as_inline_table_mut() {
+                    definition.fmt();
+                }
+                version
+            }
+            Migration::Missing => unreachable!("missing projects abort the migration"),
+        };
+
+        lockfile.section_mut(project_type).insert(
+            name.clone(),
+            Package::new(version, definition.side.clone())?,
+        );
+    }
+
+    ensure_compatible(client, &lockfile).await?;
+
+    // The manifest is staged first and only replaces the old one once the lockfile is written
+    let partial = "podzol.toml.partial";
+    fs::write(partial, document.to_string())?;
+
+    if let Err(err) = lockfile.write(LOCKFILE) {
+        fs::remove_file(partial)?;
+        return Err(err);
+    }
+
+    fs::rename(partial, "podzol.toml")?;
+
+    println!("Migrated to minecraft {minecraft}");
+
+    Ok(())
+}
@@ -0,0 +1,115 @@
+use anyhow::Result;
+use futures_util::future::try_join_all;
+use std::{cmp::Reverse, fs};
+
+use crate::{
+    ProjectType,
+    lockfile::{LOCKFILE, Lockfile},
+    manifest::Manifest,
+    registry::Registry,
+};
+
+pub async fn outdated(client: &impl Registry, changelog: bool) -> Result<()> {
+    let manifest: Manifest = toml_edit::de::from_slice(&fs::read("podzol.toml")?)?;
+    let lockfile = Lockfile::read(LOCKFILE)?;
+    let manifest = &manifest;
+    let enviroment = &manifest.enviroment;
+
+    let candidates = ProjectType::ALL.into_iter().flat_map(|project_type| {
+        let mut definitions: Vec<_> = manifest
+            .section(project_type)
+            .iter()
+            .map(|(name, definition)| {
+                // Constraints are compared through the version they are locked to
+                let current = match lockfile.section(project_type).get(name) {
+                    Some(package) => package.version.clone(),
+                    None => definition.version.to_string(),
+                };
+
+                (name, definition, current)
+            })
+            .collect();
+        definitions.sort_by_key(|(name, _, _)| *name);
+        definitions
+    });
+
+    let results = try_join_all(candidates.map(|(name, definition, current)| async move {
+        let mut versions = client
+            .get_project_versions(name, &enviroment.minecraft, &enviroment.loaders)
+            .await?;
+
+        let strategy = manifest.strategy(definition);
+
+        // The pinned version is kept even if the strategy wouldn't pick it anymore
+        versions.retain(|version| version.version_number == current || strategy.allows(version));
+        versions.sort_by_key(|version| Reverse(version.date_published));
+
+        anyhow::Ok((name, current, versions))
+    }))
+    .await?;
+
+    let mut outdated = 0;
+
+    for (name, current, mut versions) in results {
+        let newer = match versions
+            .iter()
+            .position(|version| version.version_number == current)
+        {
+            Some(0) => continue,
+            Some(position) => {
+                versions.truncate(position);
+                versions
+            }
+            None => {
+                println!(
+                    "{name}: {current} is not available for this pack, latest is {}",
+                    versions
+                        .first()
+                        .map_or("unknown", |version| &version.version_number)
+                );
+                outdated += 1;
+                continue;
+            }
+        };
+
+        let latest = &newer[0];
+
+        println!(
+            "{name}: {current} → {} ({} version(s) behind, latest is a {})",
+            latest.version_number,
+            newer.len(),
+            latest.version_type
+        );
+        outdated += 1;
+
+        if !changelog {
+            continue;
+        }
+
+        for version in &newer {
+            println!(
+                "\n## {} ({}, {})\n",
+                version.version_number,
+                version.version_type,
+                version.date_published.format("%Y-%m-%d")
+            );
+            println!(
+                "{}",
+                version
+                    .changelog
+                    .as_deref()
+                    .filter(|changelog| !changelog.trim().is_empty())
+                    .unwrap_or("No changelog provided")
+                    .trim()
+            );
+        }
+
+        println!();
+    }
+
+    if outdated == 0 {
+        println!("Everything is up to date");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,46 @@
+use anyhow::{Result, bail};
+use std::fs;
+use toml_edit::DocumentMut;
+
+use crate::{
+    ProjectType,
+    lockfile::{LOCKFILE, Lockfile},
+};
+
+pub fn remove(projects: Vec<String>, project_type: ProjectType) -> Result<()> {
+    let manifest_src = fs::read_to_string("podzol.toml")?;
+    let mut document: DocumentMut = manifest_src.parse()?;
+    let mut lockfile = Lockfile::read(LOCKFILE)?;
+
+    let table = project_type.as_table();
+
+    let missing: Vec<_> = projects
+        .iter()
+        .filter(|name| {
+            !document
+                .get(table)
+                .and_then(|item| item.as_table_like())
+                .is_some_and(|table| table.contains_key(name))
+        })
+        .map(String::as_str)
+        .collect();
+
+    if !missing.is_empty() {
+        bail!("Not present in {table}: {}", missing.join(", "));
+    }
+
+    for name in projects {
+        if let Some(table) = document[table].as_table_like_mut() {
+            table.remove(&name);
+        }
+
+        lockfile.section_mut(project_type).remove(&name);
+
+        println!("Removed {name} from {table}");
+    }
+
+    fs::write("podzol.toml", document.to_string())?;
+    lockfile.write(LOCKFILE)?;
+
+    Ok(())
+}
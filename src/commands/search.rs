@@ -0,0 +1,80 @@
+use anyhow::Result;
+use indicatif::HumanCount;
+use inquire::MultiSelect;
+use std::fs;
+
+use crate::{ProjectType, commands::add, manifest::Manifest, registry::Registry};
+
+pub async fn search(
+    client: &impl Registry,
+    query: &str,
+    project_type: ProjectType,
+    limit: usize,
+    interactive: bool,
+) -> Result<()> {
+    let manifest: Manifest = toml_edit::de::from_slice(&fs::read("podzol.toml")?)?;
+
+    let results = client
+        .search(
+            query,
+            project_type,
+            &manifest.enviroment.minecraft,
+            &manifest.enviroment.loaders,
+            limit,
+        )
+        .await?;
+
+    if results.hits.is_empty() {
+        println!("No compatible projects found");
+        return Ok(());
+    }
+
+    if !interactive {
+        for hit in &results.hits {
+            println!(
+                "{} - {} ({}, {} downloads)\n    {}",
+                hit.slug,
+                hit.title,
+                hit.get_side(),
+                HumanCount(hit.downloads),
+                hit.description
+            );
+        }
+
+        println!(
+            "\nShowing {} of {} result(s)",
+            results.hits.len(),
+            results.total_hits
+        );
+
+        return Ok(());
+    }
+
+    let options: Vec<_> = results
+        .hits
+        .iter()
+        .map(|hit| {
+            format!(
+                "{} - {} ({}, {} downloads): {}",
+                hit.slug,
+                hit.title,
+                hit.get_side(),
+                HumanCount(hit.downloads),
+                hit.description
+            )
+        })
+        .collect();
+
+    let selected = MultiSelect::new("Projects to add", options).raw_prompt()?;
+
+    if selected.is_empty() {
+        return Ok(());
+    }
+
+    let projects = selected
+        .into_iter()
+        .map(|option| results.hits[option.index].slug.clone())
+        .collect();
+
+    add(client, projects, project_type, None, None).await
+}
@@ -0,0 +1,112 @@
+use anyhow::{Result, bail};
+use futures_util::future::try_join_all;
+use std::fs;
+use toml_edit::{DocumentMut, value};
+
+use crate::{
+    ProjectType,
+    constraint::VersionReq,
+    lockfile::{LOCKFILE, Lockfile, Package},
+    manifest::Manifest,
+    registry::Registry,
+};
+
+pub async fn update(client: &impl Registry, projects: Vec<String>, dry_run: bool) -> Result<()> {
+    let manifest_src = fs::read_to_string("podzol.toml")?;
+    let mut document: DocumentMut = manifest_src.parse()?;
+    let manifest: Manifest = toml_edit::de::from_document(document.clone())?;
+    let mut lockfile = Lockfile::read(LOCKFILE)?;
+    lockfile.retarget(&manifest.enviroment);
+
+    let missing: Vec<_> = projects
+        .iter()
+        .filter(|name| {
+            !ProjectType::ALL
+                .into_iter()
+                .any(|project_type| manifest.section(project_type).contains_key(*name))
+        })
+        .map(String::as_str)
+        .collect();
+
+    if !missing.is_empty() {
+        bail!("Not present in the manifest: {}", missing.join(", "));
+    }
+
+    let candidates = ProjectType::ALL.into_iter().flat_map(|project_type| {
+        manifest
+            .section(project_type)
+            .iter()
+            .filter(|(name, _)| projects.is_empty() || projects.contains(name))
+            .map(move |(name, definition)| (project_type, name, definition))
+    });
+
+    let manifest = &manifest;
+    let enviroment = &manifest.enviroment;
+
+    let results = try_join_all(
+        candidates.map(|(project_type, name, definition)| async move {
+            // Exact versions are moved forward, constraints only within their range
+            let req = match &definition.version {
+                VersionReq::Exact(_) => &VersionReq::Latest,
+                req => req,
+            };
+
+            let version = client
+                .get_version(
+                    name,
+                    &enviroment.minecraft,
+                    &enviroment.loaders,
+                    req,
+                    &manifest.strategy(definition),
+                )
+                .await?;
+
+            anyhow::Ok((project_type, name, definition, version))
+        }),
+    )
+    .await?;
+
+    let mut updated = 0;
+
+    for (project_type, name, definition, version) in results {
+        let current = match lockfile.section(project_type).get(name) {
+            Some(package) => package.version.clone(),
+            None => definition.version.to_string(),
+        };
+
+        if version.version_number == current {
+            continue;
+        }
+
+        println!("{name}: {current} → {}", version.version_number);
+        updated += 1;
+
+        if dry_run {
+            continue;
+        }
+
+        if definition.version.is_exact() {
+            document[project_type.as_table()][name]["version"] = value(&version.version_number);
+        }
+
+        lockfile.section_mut(project_type).insert(
+            name.clone(),
+            Package::new(version, definition.side.clone())?,
+        );
+    }
+
+    if updated == 0 {
+        println!("Everything is up to date");
+        return Ok(());
+    }
+
+    if dry_run {
+        println!("Dry run, {updated} project(s) would be updated");
+        return Ok(());
+    }
+
+    fs::write("podzol.toml", document.to_string())?;
+    lockfile.write(LOCKFILE)?;
+
+    Ok(())
+}
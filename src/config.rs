@@ -0,0 +1,42 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::{env, fs, io::ErrorKind, path::PathBuf};
+
+/// User wide settings, read from `podzol/config.toml` in the platform config directory
+/// (`$XDG_CONFIG_HOME` on Linux) or the file `PODZOL_CONFIG` points to
+#[derive(Debug, Default, Deserialize)]
+pub struct Config {
+    #[serde(default)]
+    pub modrinth: ModrinthSettings,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ModrinthSettings {
+    /// A personal access token, needed to see private and draft projects
+    pub token: Option<String>,
+}
+
+impl Config {
+    pub fn path() -> Option<PathBuf> {
+        match env::var_os("PODZOL_CONFIG") {
+            Some(path) => Some(PathBuf::from(path)),
+            None => dirs::config_dir().map(|dir| dir.join("podzol").join("config.toml")),
+        }
+    }
+
+    /// Reads the config file, falling back to the defaults if there is none
+    pub fn read() -> Result<Self> {
+        let Some(path) = Self::path() else {
+            return Ok(Self::default());
+        };
+
+        let data = match fs::read(&path) {
+            Ok(data) => data,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Self::default()),
+            Err(err) => return Err(err.into()),
+        };
+
+        toml_edit::de::from_slice(&data)
+            .with_context(|| format!("Failed to parse {}", path.display()))
+    }
+}
@@ -0,0 +1,185 @@
+use anyhow::{Result, anyhow, bail};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+};
+
+use crate::{
+    ProjectType,
+    lockfile::{Lockfile, Package},
+    modrinth::DependencyType,
+    registry::Registry,
+};
+
+/// Two entries of the pack where at least one declares the other as incompatible.
+#[derive(Debug)]
+pub struct Conflict {
+    pub project: String,
+    pub version: String,
+    pub other: String,
+    pub other_version: String,
+}
+
+impl Display for Conflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} is incompatible with {} {}",
+            self.project, self.version, self.other, self.other_version
+        )
+    }
+}
+
+/// Loads the dependency metadata of every locked version and collects all incompatible pairs.
+pub async fn find_conflicts(client: &impl Registry, lockfile: &Lockfile) -> Result<Vec<Conflict>> {
+    let packages: Vec<(&String, &Package)> = ProjectType::ALL
+        .into_iter()
+        .flat_map(|project_type| lockfile.section(project_type))
+        .collect();
+
+    let ids: Vec<_> = packages
+        .iter()
+        .map(|(_, package)| package.version_id.clone())
+        .collect();
+
+    let versions: HashMap<_, _> = client
+        .get_versions(&ids)
+        .await?
+        .into_iter()
+        .map(|version| (version.id.clone(), version))
+        .collect();
+
+    let mut reported = HashSet::new();
+    let mut conflicts = Vec::new();
+
+    for (name, package) in &packages {
+        let version = versions.get(&package.version_id).ok_or_else(|| {
+            anyhow!(
+                "{name} is locked to version {}, which no longer exists",
+                package.version
+            )
+        })?;
+
+        let incompatible = version
+            .dependencies
+            .iter()
+            .filter(|dependency| dependency.dependency_type == DependencyType::Incompatible);
+
+        for dependency in incompatible {
+            for (other_name, other) in &packages {
+                if other.project_id == package.project_id {
+                    continue;
+                }
+
+                // A version ID narrows the incompatibility down to that exact version
+                let matches = match (&dependency.project_id, &dependency.version_id) {
+                    (_, Some(version_id)) => *version_id == other.version_id,
+                    (Some(project_id), None) => *project_id == other.project_id,
+                    (None, None) => false,
+                };
+
+                if !matches {
+                    continue;
+                }
+
+                let pair = if name < other_name {
+                    (*name, *other_name)
+                } else {
+                    (*other_name, *name)
+                };
+
+                if reported.insert(pair) {
+                    conflicts.push(Conflict {
+                        project: name.to_string(),
+                        version: package.version.clone(),
+                        other: other_name.to_string(),
+                        other_version: other.version.clone(),
+                    });
+                }
+            }
+        }
+    }
+
+    Ok(conflicts)
+}
+
+/// Fails with every conflict listed if the pack contains any.
+pub async fn ensure_compatible(client: &impl Registry, lockfile: &Lockfile) -> Result<()> {
+    let conflicts = find_conflicts(client, lockfile).await?;
+
+    if conflicts.is_empty() {
+        return Ok(());
+    }
+
+    let list = conflicts
+        .iter()
+        .map(|conflict| format!("  - {conflict}"))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    bail!("Found incompatible projects:\n{list}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        manifest::Side,
+        modrinth::Version,
+        registry::fake::{Fake, dependency, version},
+    };
+
+    fn lock(versions: &[&Version]) -> Lockfile {
+        let mut lockfile = Lockfile::default();
+
+        for version in versions {
+            lockfile.mods.insert(
+                version.project_id.to_lowercase(),
+                Package::new((*version).clone(), Side::Both).unwrap(),
+            );
+        }
+
+        lockfile
+    }
+
+    #[tokio::test]
+    async fn mutual_incompatibility_is_reported_once() {
+        let mut a = version("a1", "A", "1.0.0", 0);
+        a.dependencies = vec![dependency(Some("B"), None, DependencyType::Incompatible)];
+        let mut b = version("b1", "B", "1.0.0", 0);
+        b.dependencies = vec![dependency(Some("A"), None, DependencyType::Incompatible)];
+
+        let client = Fake::default().version(a.clone()).version(b.clone());
+        let conflicts = find_conflicts(&client, &lock(&[&a, &b])).await.unwrap();
+
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(
+            conflicts[0].to_string(),
+            "a 1.0.0 is incompatible with b 1.0.0"
+        );
+    }
+
+    #[tokio::test]
+    async fn version_id_narrows_the_incompatibility() {
+        let mut a = version("a1", "A", "1.0.0", 0);
+        a.dependencies = vec![dependency(
+            Some("B"),
+            Some("b1"),
+            DependencyType::Incompatible,
+        )];
+        let b1 = version("b1", "B", "1.0.0", 0);
+        let b2 = version("b2", "B", "2.0.0", 1);
+
+        let client = Fake::default()
+            .version(a.clone())
+            .version(b1.clone())
+            .version(b2.clone());
+
+        let conflicts = find_conflicts(&client, &lock(&[&a, &b2])).await.unwrap();
+        assert!(conflicts.is_empty());
+
+        let conflicts = find_conflicts(&client, &lock(&[&a, &b1])).await.unwrap();
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].other_version, "1.0.0");
+    }
+}
@@ -0,0 +1,438 @@
+use std::{cmp::Ordering, fmt::Display, str::FromStr};
+
+use serde_with::{DeserializeFromStr, SerializeDisplay};
+
+use crate::{manifest::Strategy, modrinth::Version};
+
+/// The version of a project as requested in the manifest.
+///
+/// Plain strings keep their historical meaning and must match a `version_number` exactly,
+/// anything starting with an operator is treated as a constraint.
+#[derive(Debug, DeserializeFromStr, SerializeDisplay, Clone, PartialEq, Eq)]
+pub enum VersionReq {
+    /// The newest version allowed by the strategy
+    Latest,
+    /// The newest stable release, regardless of the strategy
+    LatestRelease,
+    Exact(String),
+    Constraint(Vec<Comparator>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Comparator {
+    pub op: Op,
+    pub version: LenientVersion,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Exact,
+    Greater,
+    GreaterEq,
+    Less,
+    LessEq,
+    Tilde,
+    Caret,
+    Wildcard,
+}
+
+/// A dotted numeric version, compared component by component with missing ones being zero.
+#[derive(Debug, Clone)]
+pub struct LenientVersion(pub Vec<u64>);
+
+impl VersionReq {
+    pub const fn is_exact(&self) -> bool {
+        matches!(self, Self::Exact(_))
+    }
+
+    /// Whether the Modrinth `version_number` satisfies this requirement
+    pub fn matches(&self, version_number: &str, minecraft: &str) -> bool {
+        match self {
+            Self::Latest | Self::LatestRelease => true,
+            Self::Exact(version) => version == version_number,
+            Self::Constraint(comparators) => LenientVersion::extract(version_number, minecraft)
+                .is_some_and(|version| {
+                    comparators
+                        .iter()
+                        .all(|comparator| comparator.matches(&version))
+                }),
+        }
+    }
+
+    /// Picks the version to use out of the ones compatible with the pack
+    pub fn select(
+        &self,
+        versions: Vec<Version>,
+        minecraft: &str,
+        strategy: &Strategy,
+    ) -> Option<Version> {
+        match self {
+            Self::Exact(version) => versions.into_iter().find(|v| v.version_number == *version),
+            Self::Latest => strategy.select(versions),
+            Self::LatestRelease => Strategy::Release.select(versions),
+            Self::Constraint(_) => strategy.select(
+                versions
+                    .into_iter()
+                    .filter(|version| self.matches(&version.version_number, minecraft))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl FromStr for VersionReq {
+    type Err = String;
+
+    fn from_str(req: &str) -> Result<Self, Self::Err> {
+        let req = req.trim();
+
+        match req {
+            "latest" => return Ok(Self::Latest),
+            "latest-release" => return Ok(Self::LatestRelease),
+            _ => {}
+        }
+
+        if !req.starts_with(['^', '~', '=', '>', '<', '*']) {
+            return Ok(Self::Exact(req.to_string()));
+        }
+
+        req.split(',')
+            .map(str::parse)
+            .collect::<Result<_, _>>()
+            .map(Self::Constraint)
+    }
+}
+
+impl Display for VersionReq {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Latest => write!(f, "latest"),
+            Self::LatestRelease => write!(f, "latest-release"),
+            Self::Exact(version) => write!(f, "{version}"),
+            Self::Constraint(comparators) => {
+                for (i, comparator) in comparators.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{comparator}")?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Comparator {
+    pub fn matches(&self, version: &LenientVersion) -> bool {
+        let bound = &self.version;
+
+        match self.op {
+            Op::Exact => version == bound,
+            Op::Greater => version > bound,
+            Op::GreaterEq => version >= bound,
+            Op::Less => version < bound,
+            Op::LessEq => version <= bound,
+            Op::Tilde => {
+                version >= bound && *version < bound.bump(if bound.0.len() > 1 { 1 } else { 0 })
+            }
+            Op::Caret => {
+                // The first non-zero component is the one that must not change
+                let index = bound
+                    .0
+                    .iter()
+                    .position(|component| *component != 0)
+                    .unwrap_or(bound.0.len().saturating_sub(1));
+
+                version >= bound && *version < bound.bump(index)
+            }
+            Op::Wildcard => true,
+        }
+    }
+}
+
+impl FromStr for Comparator {
+    type Err = String;
+
+    fn from_str(comparator: &str) -> Result<Self, Self::Err> {
+        let comparator = comparator.trim();
+
+        if comparator == "*" {
+            return Ok(Self {
+                op: Op::Wildcard,
+                version: LenientVersion(Vec::new()),
+            });
+        }
+
+        let (op, version) = [
+            (">=", Op::GreaterEq),
+            ("<=", Op::LessEq),
+            (">", Op::Greater),
+            ("<", Op::Less),
+            ("=", Op::Exact),
+            ("~", Op::Tilde),
+            ("^", Op::Caret),
+        ]
+        .into_iter()
+        .find_map(|(prefix, op)| {
+            comparator
+                .strip_prefix(prefix)
+                .map(|version| (op, version.trim()))
+        })
+        .unwrap_or((Op::Caret, comparator));
+
+        Ok(Self {
+            op,
+            version: version.parse()?,
+        })
+    }
+}
+
+impl Display for Comparator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let op = match self.op {
+            Op::Wildcard => return write!(f, "*"),
+            Op::Exact => "=",
+            Op::Greater => ">",
+            Op::GreaterEq => ">=",
+            Op::Less => "<",
+            Op::LessEq => "<=",
+            Op::Tilde => "~",
+            Op::Caret => "^",
+        };
+
+        write!(f, "{op}{}", self.version)
+    }
+}
+
+impl LenientVersion {
+    /// Pulls the comparable part out of a messy Minecraft mod version string.
+    ///
+    /// `mc1.21.1-0.6.5-fabric`, `1.21-0.6.5` and `0.6.5+1.21.1` all yield `0.6.5`: tokens that
+    /// look like a Minecraft version are skipped unless nothing else is left.
+    pub fn extract(version_number: &str, minecraft: &str) -> Option<Self> {
+        let mut fallback = None;
+
+        for token in version_number.split(['-', '+', '_', ' ']) {
+            let is_minecraft = is_minecraft(token, minecraft);
+            let token = token.trim_start_matches(['v', 'V']);
+
+            let end = token
+                .find(|c: char| !c.is_ascii_digit() && c != '.')
+                .unwrap_or(token.len());
+            let numeric = token[..end].trim_end_matches('.');
+
+            let Ok(version) = numeric.parse::<Self>() else {
+                continue;
+            };
+
+            if !is_minecraft {
+                return Some(version);
+            }
+
+            fallback.get_or_insert(version);
+        }
+
+        fallback
+    }
+
+    /// The smallest version greater than every version sharing the components up to `index`
+    fn bump(&self, index: usize) -> Self {
+        let mut components: Vec<_> = self.0.iter().copied().take(index + 1).collect();
+        components.resize(index + 1, 0);
+        components[index] += 1;
+
+        Self(components)
+    }
+}
+
+/// Whether a version token names a Minecraft version rather than the project's own: the
+/// pack's version or a prefix of it (`1.21` on 1.21.1), an `mc` prefixed one, or any release
+/// since 1.14, optionally as a wildcard like `1.20.x`
+fn is_minecraft(token: &str, minecraft: &str) -> bool {
+    if token.starts_with("mc") || token == minecraft {
+        return true;
+    }
+
+    let token = token.strip_suffix(".x").unwrap_or(token);
+
+    if minecraft
+        .strip_prefix(token)
+        .is_some_and(|rest| rest.starts_with('.'))
+    {
+        return true;
+    }
+
+    let components: Vec<_> = token.split('.').collect();
+
+    match components[..] {
+        ["1", minor] | ["1", minor, _] => {
+            minor.parse::<u64>().is_ok_and(|minor| minor >= 14)
+                && components
+                    .iter()
+                    .all(|component| component.parse::<u64>().is_ok())
+        }
+        _ => false,
+    }
+}
+
+impl FromStr for LenientVersion {
+    type Err = String;
+
+    fn from_str(version: &str) -> Result<Self, Self::Err> {
+        let version = version.trim().trim_start_matches(['v', 'V']);
+
+        if version.is_empty() {
+            return Err("Empty version".to_string());
+        }
+
+        version
+            .split('.')
+            .map(|component| {
+                component
+                    .parse()
+                    .map_err(|_| format!("Invalid version '{version}'"))
+            })
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+}
+
+impl Display for LenientVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, component) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, ".")?;
+            }
+            write!(f, "{component}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl PartialEq for LenientVersion {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl Eq for LenientVersion {}
+
+impl PartialOrd for LenientVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for LenientVersion {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let len = self.0.len().max(other.0.len());
+        let component = |version: &Self, i| version.0.get(i).copied().unwrap_or(0);
+
+        (0..len)
+            .map(|i| component(self, i).cmp(&component(other, i)))
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or(Ordering::Equal)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extract() {
+        let cases = [
+            ("0.6.5", Some("0.6.5")),
+            ("mc1.21.1-0.6.5-fabric", Some("0.6.5")),
+            ("0.6.5+1.21.1", Some("0.6.5")),
+            ("1.21-0.6.5", Some("0.6.5")),
+            ("1.21.x-0.6.5", Some("0.6.5")),
+            ("1.20.x-0.5.11", Some("0.5.11")),
+            ("1.20.1-0.5.11", Some("0.5.11")),
+            ("v2.3.0-beta.1", Some("2.3.0")),
+            ("fabric-api 0.100.0+1.21", Some("0.100.0")),
+            ("1.5.2", Some("1.5.2")),
+            ("1.21.1", Some("1.21.1")),
+            ("1.21.1-1.20.x", Some("1.21.1")),
+            ("beta", None),
+        ];
+
+        for (version_number, expected) in cases {
+            assert_eq!(
+                LenientVersion::extract(version_number, "1.21.1")
+                    .map(|version| version.to_string()),
+                expected.map(str::to_string),
+                "{version_number}"
+            );
+        }
+    }
+
+    #[test]
+    fn parse() {
+        let cases = [
+            ("latest", VersionReq::Latest),
+            ("latest-release", VersionReq::LatestRelease),
+            ("0.6.5", VersionReq::Exact("0.6.5".to_string())),
+            (
+                "mc1.21.1-0.6.5",
+                VersionReq::Exact("mc1.21.1-0.6.5".to_string()),
+            ),
+        ];
+
+        for (req, expected) in cases {
+            assert_eq!(req.parse::<VersionReq>(), Ok(expected), "{req}");
+        }
+
+        let cases = [
+            ("^0.6", "^0.6"),
+            ("~1.2", "~1.2"),
+            (">=0.5,<0.7", ">=0.5, <0.7"),
+            ("> 1 , <= 2.0.1", ">1, <=2.0.1"),
+            ("*", "*"),
+        ];
+
+        for (req, expected) in cases {
+            let parsed: VersionReq = req.parse().unwrap();
+            assert!(matches!(parsed, VersionReq::Constraint(_)), "{req}");
+            assert_eq!(parsed.to_string(), expected, "{req}");
+        }
+
+        assert!("^".parse::<VersionReq>().is_err());
+        assert!(">=0.5,<x".parse::<VersionReq>().is_err());
+    }
+
+    #[test]
+    fn matches() {
+        let cases = [
+            ("^0.6", "0.6.5", true),
+            ("^0.6", "0.7.0", false),
+            ("^0.6", "0.5.9", false),
+            ("^1.2", "1.9.3", true),
+            ("^1.2", "2.0.0", false),
+            ("^0.0.3", "0.0.4", false),
+            ("~1.2", "1.2.9", true),
+            ("~1.2", "1.3.0", false),
+            (">=0.5,<0.7", "0.6.5", true),
+            (">=0.5,<0.7", "0.7", false),
+            ("=0.6.5", "0.6.5.0", true),
+            ("*", "12.0", true),
+            ("^0.6", "1.21-0.6.5", true),
+            ("^0.6", "mc1.21.1-0.6.5-fabric", true),
+            ("latest", "anything", true),
+            ("latest-release", "anything", true),
+            ("0.6.5", "0.6.5", true),
+            ("0.6.5", "0.6.5+1.21.1", false),
+        ];
+
+        for (req, version_number, expected) in cases {
+            let req: VersionReq = req.parse().unwrap();
+            assert_eq!(
+                req.matches(version_number, "1.21.1"),
+                expected,
+                "{req} against {version_number}"
+            );
+        }
+    }
+}
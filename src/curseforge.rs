@@ -0,0 +1,251 @@
+use std::{collections::HashMap, env};
+
+use anyhow::{Result, anyhow};
+use reqwest::{Client as HttpClient, RequestBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::{http, manifest::Loader};
+
+const DEFAULT_URL: &str = "https://api.curseforge.com/v1";
+const MINECRAFT_GAME_ID: u32 = 432;
+
+/// The `manifest.json` at the root of a CurseForge modpack zip
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Manifest {
+    pub minecraft: Minecraft,
+    pub manifest_type: String,
+    pub manifest_version: u32,
+    pub name: String,
+    pub version: String,
+    #[serde(default)]
+    pub author: String,
+    pub files: Vec<ManifestFile>,
+    #[serde(default = "default_overrides")]
+    pub overrides: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Minecraft {
+    pub version: String,
+    pub mod_loaders: Vec<ModLoader>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ModLoader {
+    pub id: String,
+    pub primary: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ManifestFile {
+    #[serde(rename = "projectID")]
+    pub project_id: u64,
+    #[serde(rename = "fileID")]
+    pub file_id: u64,
+    pub required: bool,
+}
+
+fn default_overrides() -> String {
+    "overrides".to_string()
+}
+
+impl ModLoader {
+    /// Splits an id such as `fabric-0.16.10` into the loader and its version
+    pub fn parse(&self) -> Option<(Loader, String)> {
+        let (loader, version) = self.id.split_once('-')?;
+
+        Some((loader.parse().ok()?, version.to_string()))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Data<T> {
+    data: T,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct File {
+    pub id: u64,
+    pub mod_id: u64,
+    pub display_name: String,
+    pub hashes: Vec<FileHash>,
+    pub file_fingerprint: u32,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FingerprintMatches {
+    exact_matches: Vec<FingerprintMatch>,
+}
+
+#[derive(Debug, Deserialize)]
+struct FingerprintMatch {
+    file: File,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FileHash {
+    pub value: String,
+    pub algo: u32,
+}
+
+impl File {
+    pub fn sha1(&self) -> Option<&str> {
+        // CurseForge identifies sha1 as algorithm 1 and md5 as 2
+        self.hashes
+            .iter()
+            .find(|hash| hash.algo == 1)
+            .map(|hash| hash.value.as_str())
+    }
+}
+
+/// Computes the fingerprint CurseForge identifies files by.
+///
+/// It's a 32 bit MurmurHash2 with a seed of 1 over the file with all whitespace bytes removed.
+pub fn fingerprint(data: &[u8]) -> u32 {
+    let data: Vec<_> = data
+        .iter()
+        .copied()
+        .filter(|byte| !matches!(byte, 9 | 10 | 13 | 32))
+        .collect();
+
+    murmur2(&data, 1)
+}
+
+fn murmur2(data: &[u8], seed: u32) -> u32 {
+    const M: u32 = 0x5bd1e995;
+
+    let mut hash = seed ^ data.len() as u32;
+
+    let mut chunks = data.chunks_exact(4);
+    for chunk in &mut chunks {
+        let mut k = u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+        k = k.wrapping_mul(M);
+        k ^= k >> 24;
+        k = k.wrapping_mul(M);
+
+        hash = hash.wrapping_mul(M) ^ k;
+    }
+
+    let remainder = chunks.remainder();
+    if remainder.len() >= 3 {
+        hash ^= u32::from(remainder[2]) << 16;
+    }
+    if remainder.len() >= 2 {
+        hash ^= u32::from(remainder[1]) << 8;
+    }
+    if !remainder.is_empty() {
+        hash ^= u32::from(remainder[0]);
+        hash = hash.wrapping_mul(M);
+    }
+
+    hash ^= hash >> 13;
+    hash = hash.wrapping_mul(M);
+    hash ^ (hash >> 15)
+}
+
+#[derive(Clone)]
+pub struct Client {
+    http_client: HttpClient,
+    base_url: String,
+    api_key: Option<String>,
+}
+
+impl Client {
+    /// Creates a client for the CurseForge API.
+    ///
+    /// The API key is read from `CURSEFORGE_API_KEY` and `PODZOL_CURSEFORGE_URL` can point
+    /// it at a different server, such as a local stand-in.
+    pub fn new() -> Result<Self> {
+        Ok(Self {
+            http_client: http::client()?,
+            base_url: env::var("PODZOL_CURSEFORGE_URL").unwrap_or_else(|_| DEFAULT_URL.to_string()),
+            api_key: env::var("CURSEFORGE_API_KEY").ok(),
+        })
+    }
+
+    fn post(&self, path: &str) -> Result<RequestBuilder> {
+        let api_key = self.api_key.as_deref().ok_or_else(|| {
+            anyhow!("The CurseForge API requires a key, set it through CURSEFORGE_API_KEY")
+        })?;
+
+        Ok(self
+            .http_client
+            .post(format!("{}{path}", self.base_url))
+            .header("x-api-key", api_key))
+    }
+
+    pub async fn get_files(&self, ids: &[u64]) -> Result<Vec<File>> {
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let res: Data<_> = self
+            .post("/mods/files")?
+            .json(&serde_json::json!({ "fileIds": ids }))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        Ok(res.data)
+    }
+
+    /// Looks up the files matching the given fingerprints, keyed by fingerprint
+    pub async fn get_fingerprint_matches(
+        &self,
+        fingerprints: &[u32],
+    ) -> Result<HashMap<u32, File>> {
+        if fingerprints.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let res: Data<FingerprintMatches> = self
+            .post(&format!("/fingerprints/{MINECRAFT_GAME_ID}"))?
+            .json(&serde_json::json!({ "fingerprints": fingerprints }))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        Ok(res
+            .data
+            .exact_matches
+            .into_iter()
+            .map(|exact| (exact.file.file_fingerprint, exact.file))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// SMHasher's verification: every prefix of 0..=255 hashed with seed 256 - length, then the
+    /// concatenated hashes hashed with seed 0
+    #[test]
+    fn murmur2_verification() {
+        let key: Vec<u8> = (0..=255).collect();
+
+        let hashes: Vec<u8> = (0..256)
+            .flat_map(|len| murmur2(&key[..len], 256 - len as u32).to_le_bytes())
+            .collect();
+
+        assert_eq!(murmur2(&hashes, 0), 0x27864c1e);
+    }
+
+    #[test]
+    fn fingerprint_ignores_whitespace() {
+        let data = b"{\n\t\"schemaVersion\": 1,\r\n  \"id\": \"sodium\"\n}\n";
+        let stripped = b"{\"schemaVersion\":1,\"id\":\"sodium\"}";
+
+        assert_eq!(fingerprint(data), fingerprint(stripped));
+        assert_eq!(fingerprint(stripped), murmur2(stripped, 1));
+        assert_ne!(fingerprint(b"ab"), fingerprint(b"ba"));
+    }
+}
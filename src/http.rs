@@ -0,0 +1,30 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use reqwest::Client;
+use rustls::crypto::aws_lc_rs;
+use rustls_platform_verifier::BuilderVerifierExt;
+
+/// Identifies podzol to the APIs it talks to, as Modrinth asks every client to
+const USER_AGENT: &str = concat!(
+    "podzol/",
+    env!("CARGO_PKG_VERSION"),
+    " (",
+    env!("CARGO_PKG_REPOSITORY"),
+    ")"
+);
+
+/// Builds the HTTP client shared by every API podzol talks to
+pub fn client() -> Result<Client> {
+    let client = Client::builder()
+        .user_agent(USER_AGENT)
+        .use_preconfigured_tls(
+            rustls::ClientConfig::builder_with_provider(Arc::new(aws_lc_rs::default_provider()))
+                .with_safe_default_protocol_versions()?
+                .with_platform_verifier()
+                .with_no_client_auth(),
+        )
+        .build()?;
+
+    Ok(client)
+}
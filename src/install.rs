@@ -0,0 +1,157 @@
+use anyhow::{Context, Result, bail};
+use futures_util::{StreamExt, TryStreamExt, stream};
+use indicatif::{ProgressBar, ProgressStyle};
+use std::{
+    collections::BTreeSet,
+    fs,
+    io::ErrorKind,
+    path::{Component, Path, PathBuf},
+};
+
+use crate::{lockfile::Package, manifest::Override, modrinth::sha512, registry::Registry};
+
+/// Keeps track of every file podzol put in a directory, relative to it
+const RECORD: &str = ".podzol-installed.json";
+
+const CONCURRENT_DOWNLOADS: usize = 8;
+
+/// Rejects relative paths that would escape the directory they get joined onto
+pub fn sanitize(path: &Path) -> Result<&Path> {
+    let mut components = path.components().peekable();
+
+    if components.peek().is_some()
+        && components.all(|component| matches!(component, Component::Normal(_)))
+    {
+        Ok(path)
+    } else {
+        bail!("Refusing to write to {}", path.display())
+    }
+}
+
+/// Installs packages and overrides into `root`.
+///
+/// Packages already present with the right hash are not downloaded again and files installed
+/// by a previous run that are no longer part of the pack are removed.
+pub async fn install(
+    client: &impl Registry,
+    root: &Path,
+    packages: Vec<(PathBuf, &Package)>,
+    overrides: Vec<Override>,
+) -> Result<()> {
+    fs::create_dir_all(root)?;
+
+    let record = root.join(RECORD);
+    let previous: BTreeSet<PathBuf> = if fs::exists(&record)? {
+        serde_json::from_slice(&fs::read(&record)?)?
+    } else {
+        BTreeSet::new()
+    };
+
+    // Anything listed in the record gets deleted once it's no longer part of the pack
+    for path in &previous {
+        sanitize(path).with_context(|| format!("{} is corrupted", record.display()))?;
+    }
+
+    let mut installed = BTreeSet::new();
+    let mut pending = Vec::new();
+
+    for (path, package) in packages {
+        let target = root.join(sanitize(&path)?);
+
+        if fs::exists(&target)? && sha512(&fs::read(&target)?) == package.sha512 {
+            installed.insert(path);
+        } else {
+            pending.push((path, package));
+        }
+    }
+
+    let up_to_date = installed.len();
+
+    let pb = ProgressBar::new(pending.len() as u64);
+    pb.set_style(
+        ProgressStyle::default_bar()
+            .template("[{elapsed_precise}] {bar:40.cyan/blue} {pos:>7}/{len:7} {msg}")
+            .unwrap(),
+    );
+    pb.set_message("Downloading");
+
+    let downloaded: Vec<_> = stream::iter(pending)
+        .map(|(path, package)| {
+            let target = root.join(&path);
+            let pb = pb.clone();
+
+            async move {
+                let data = client.download(&package.url, &package.sha512).await?;
+
+                if let Some(parent) = target.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::write(target, data)?;
+
+                pb.inc(1);
+                anyhow::Ok(path)
+            }
+        })
+        .buffer_unordered(CONCURRENT_DOWNLOADS)
+        .try_collect()
+        .await?;
+
+    pb.finish_and_clear();
+
+    let downloads = downloaded.len();
+    installed.extend(downloaded);
+
+    for Override { source, target, .. } in overrides {
+        let destination = root.join(&target);
+
+        if let Some(parent) = destination.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(source, destination)?;
+
+        installed.insert(target);
+    }
+
+    for stale in previous.difference(&installed) {
+        match fs::remove_file(root.join(stale)) {
+            Ok(()) => println!("Removed {}", stale.display()),
+            Err(err) if err.kind() == ErrorKind::NotFound => {}
+            Err(err) => return Err(err.into()),
+        }
+    }
+
+    fs::write(&record, serde_json::to_vec_pretty(&installed)?)?;
+
+    println!(
+        "Installed into {}: {downloads} downloaded, {up_to_date} up to date",
+        root.display()
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sanitize_rejects_escaping_paths() {
+        for path in [
+            "mods/sodium.jar",
+            "config/sodium/options.json",
+            "sodium.jar",
+        ] {
+            assert!(sanitize(Path::new(path)).is_ok(), "{path}");
+        }
+
+        for path in [
+            "",
+            "../sodium.jar",
+            "mods/../../sodium.jar",
+            "/etc/passwd",
+            "./a",
+        ] {
+            assert!(sanitize(Path::new(path)).is_err(), "{path}");
+        }
+    }
+}
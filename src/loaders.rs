@@ -0,0 +1,196 @@
+use std::collections::HashMap;
+
+use anyhow::{Result, anyhow};
+use reqwest::Client as HttpClient;
+use serde::Deserialize;
+
+use crate::{http, manifest::Loader};
+
+const FABRIC_URL: &str = "https://meta.fabricmc.net/v2";
+const QUILT_URL: &str = "https://meta.quiltmc.org/v3";
+const FORGE_PROMOTIONS_URL: &str =
+    "https://files.minecraftforge.net/net/minecraftforge/forge/promotions_slim.json";
+const FORGE_METADATA_URL: &str =
+    "https://maven.minecraftforge.net/net/minecraftforge/forge/maven-metadata.xml";
+const NEOFORGE_URL: &str =
+    "https://maven.neoforged.net/api/maven/versions/releases/net/neoforged/neoforge";
+
+#[derive(Debug)]
+pub struct LoaderVersion {
+    pub version: String,
+    pub stable: bool,
+}
+
+#[derive(Deserialize)]
+struct MetaVersion {
+    loader: MetaLoader,
+}
+
+#[derive(Deserialize)]
+struct MetaLoader {
+    version: String,
+    /// Only reported by Fabric, Quilt marks pre-releases in the version instead
+    stable: Option<bool>,
+}
+
+#[derive(Deserialize)]
+struct Promotions {
+    promos: HashMap<String, String>,
+}
+
+#[derive(Deserialize)]
+struct MavenVersions {
+    versions: Vec<String>,
+}
+
+/// Looks up loader versions from the Fabric, Quilt, Forge and NeoForge metadata services
+#[derive(Clone)]
+pub struct Client {
+    http_client: HttpClient,
+}
+
+impl Client {
+    pub fn new() -> Result<Self> {
+        Ok(Self {
+            http_client: http::client()?,
+        })
+    }
+
+    /// Every version of `loader` for this minecraft version, newest first
+    pub async fn versions(&self, loader: &Loader, minecraft: &str) -> Result<Vec<LoaderVersion>> {
+        let versions = match loader {
+            Loader::Fabric | Loader::Quilt => {
+                let url = match loader {
+                    Loader::Fabric => format!("{FABRIC_URL}/versions/loader/{minecraft}"),
+                    _ => format!("{QUILT_URL}/versions/loader/{minecraft}"),
+                };
+
+                let res: Vec<MetaVersion> = self
+                    .http_client
+                    .get(url)
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json()
+                    .await?;
+
+                res.into_iter()
+                    .map(|MetaVersion { loader }| LoaderVersion {
+                        stable: loader.stable.unwrap_or(!loader.version.contains('-')),
+                        version: loader.version,
+                    })
+                    .collect()
+            }
+            Loader::Forge => {
+                let metadata = self
+                    .http_client
+                    .get(FORGE_METADATA_URL)
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .text()
+                    .await?;
+
+                let prefix = format!("{minecraft}-");
+
+                let mut versions: Vec<_> = metadata
+                    .split("<version>")
+                    .skip(1)
+                    .filter_map(|rest| rest.split_once("</version>"))
+                    .filter_map(|(version, _)| version.strip_prefix(&prefix))
+                    .map(|version| LoaderVersion {
+                        version: version.to_string(),
+                        // Forge doesn't mark pre-releases, only recommends a build now and then
+                        stable: true,
+                    })
+                    .collect();
+
+                versions.sort_by_cached_key(|version| numeric_key(&version.version));
+                versions.reverse();
+                versions
+            }
+            Loader::NeoForge => {
+                // NeoForge drops the leading 1 of the minecraft version, 1.21.1 becomes 21.1.x
+                let prefix = minecraft
+                    .strip_prefix("1.")
+                    .map(|version| match version.split_once('.') {
+                        Some((minor, patch)) => format!("{minor}.{patch}."),
+                        None => format!("{version}.0."),
+                    })
+                    .ok_or_else(|| anyhow!("NeoForge doesn't support minecraft {minecraft}"))?;
+
+                let res: MavenVersions = self
+                    .http_client
+                    .get(NEOFORGE_URL)
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json()
+                    .await?;
+
+                let mut versions: Vec<_> = res
+                    .versions
+                    .into_iter()
+                    .filter(|version| version.starts_with(&prefix))
+                    .map(|version| LoaderVersion {
+                        stable: !version.contains('-'),
+                        version,
+                    })
+                    .collect();
+
+                versions.sort_by_cached_key(|version| numeric_key(&version.version));
+                versions.reverse();
+                versions
+            }
+        };
+
+        if versions.is_empty() {
+            return Err(anyhow!(
+                "{loader} has no versions for minecraft {minecraft}"
+            ));
+        }
+
+        Ok(versions)
+    }
+
+    /// The version of `loader` new packs should use: Forge's recommended build, otherwise the
+    /// newest stable version, or the newest one if there is no stable version yet
+    pub async fn latest(&self, loader: &Loader, minecraft: &str) -> Result<String> {
+        if *loader == Loader::Forge {
+            let promotions: Promotions = self
+                .http_client
+                .get(FORGE_PROMOTIONS_URL)
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await?;
+
+            let promoted = promotions
+                .promos
+                .get(&format!("{minecraft}-recommended"))
+                .or_else(|| promotions.promos.get(&format!("{minecraft}-latest")));
+
+            if let Some(version) = promoted {
+                return Ok(version.clone());
+            }
+        }
+
+        let mut versions = self.versions(loader, minecraft).await?;
+
+        let index = versions
+            .iter()
+            .position(|version| version.stable)
+            .unwrap_or_default();
+
+        Ok(versions.swap_remove(index).version)
+    }
+}
+
+/// Orders versions by their numeric parts, so `47.3.10` comes after `47.3.9`
+pub fn numeric_key(version: &str) -> Vec<u64> {
+    version
+        .split(|c: char| !c.is_ascii_digit())
+        .filter_map(|part| part.parse().ok())
+        .collect()
+}
@@ -1 +1,392 @@
+use anyhow::{Result, anyhow, bail};
+use futures_util::future::try_join_all;
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fs,
+    path::{Path, PathBuf},
+};
+use tokio::task;
 
+use crate::{
+    ProjectType,
+    cache::Cache,
+    install::sanitize,
+    manifest::{Definition, Enviroment, Loader, Manifest, Side},
+    modrinth::Version,
+    mrpack,
+    registry::Registry,
+};
+
+pub const LOCKFILE: &str = "podzol.lock";
+
+const LOCKFILE_VERSION: u32 = 1;
+
+/// The fully resolved state of a pack, as written to `podzol.lock`.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Lockfile {
+    pub version: u32,
+    /// The minecraft version the packages were resolved for
+    #[serde(default)]
+    pub minecraft: String,
+    /// The loaders the packages were resolved for
+    #[serde(default)]
+    pub loaders: BTreeSet<Loader>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub mods: BTreeMap<String, Package>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub resource_packs: BTreeMap<String, Package>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub shaders: BTreeMap<String, Package>,
+}
+
+/// A single locked artifact, pinned down to the exact file that gets downloaded.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Package {
+    pub project_id: String,
+    pub version_id: String,
+    pub version: String,
+    pub side: Side,
+    pub filename: String,
+    pub url: String,
+    pub size: u64,
+    pub sha1: String,
+    pub sha512: String,
+}
+
+impl Default for Lockfile {
+    fn default() -> Self {
+        Self {
+            version: LOCKFILE_VERSION,
+            minecraft: String::new(),
+            loaders: BTreeSet::new(),
+            mods: BTreeMap::new(),
+            resource_packs: BTreeMap::new(),
+            shaders: BTreeMap::new(),
+        }
+    }
+}
+
+impl Lockfile {
+    /// Reads the lockfile at `path`, returning an empty one if it doesn't exist yet.
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+
+        if !fs::exists(path)? {
+            return Ok(Self::default());
+        }
+
+        let lockfile: Self = toml_edit::de::from_slice(&fs::read(path)?)?;
+
+        if lockfile.version != LOCKFILE_VERSION {
+            return Err(anyhow!(
+                "Unsupported lockfile version {}, expected {LOCKFILE_VERSION}",
+                lockfile.version
+            ));
+        }
+
+        for (_, _, package) in lockfile.packages() {
+            check_filename(&package.filename)?;
+        }
+
+        Ok(lockfile)
+    }
+
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+
+        // Written next to the final location and renamed so it is never left half written
+        let partial = path.with_extension("lock.partial");
+        fs::write(&partial, toml_edit::ser::to_string_pretty(self)?)?;
+        fs::rename(partial, path)?;
+
+        // Not being able to register the lockfile only means cache gc won't know about it
+        if let Err(err) = Cache::new().and_then(|cache| cache.register_lockfile(path)) {
+            tracing::warn!(
+                "Failed to register {} with the cache: {err}",
+                path.display()
+            );
+        }
+
+        Ok(())
+    }
+
+    pub const fn section(&self, project_type: ProjectType) -> &BTreeMap<String, Package> {
+        match project_type {
+            ProjectType::Mod => &self.mods,
+            ProjectType::ResourcePack => &self.resource_packs,
+            ProjectType::Shader => &self.shaders,
+        }
+    }
+
+    pub const fn section_mut(
+        &mut self,
+        project_type: ProjectType,
+    ) -> &mut BTreeMap<String, Package> {
+        match project_type {
+            ProjectType::Mod => &mut self.mods,
+            ProjectType::ResourcePack => &mut self.resource_packs,
+            ProjectType::Shader => &mut self.shaders,
+        }
+    }
+
+    /// Points the lockfile at the pack's minecraft version and loaders.
+    ///
+    /// Packages resolved for another game version or set of loaders don't carry over, so they
+    /// are all dropped to be resolved again.
+    pub fn retarget(&mut self, enviroment: &Enviroment) {
+        let loaders: BTreeSet<_> = enviroment.loaders.keys().cloned().collect();
+
+        if self.minecraft == enviroment.minecraft && self.loaders == loaders {
+            return;
+        }
+
+        for project_type in ProjectType::ALL {
+            self.section_mut(project_type).clear();
+        }
+
+        self.minecraft = enviroment.minecraft.clone();
+        self.loaders = loaders;
+    }
+
+    /// Brings the lockfile in sync with the manifest.
+    ///
+    /// Entries that were removed from the manifest are dropped and only definitions
+    /// that are missing or no longer match their locked package are resolved again.
+    pub async fn resolve(&mut self, client: &impl Registry, manifest: &Manifest) -> Result<()> {
+        self.retarget(&manifest.enviroment);
+
+        let mut outdated = Vec::new();
+
+        for project_type in ProjectType::ALL {
+            let definitions = manifest.section(project_type);
+            let packages = self.section_mut(project_type);
+
+            packages.retain(|name, _| definitions.contains_key(name));
+
+            for (name, definition) in definitions {
+                if !packages.get(name).is_some_and(|package| {
+                    package.satisfies(definition, &manifest.enviroment.minecraft)
+                }) {
+                    outdated.push((project_type, name.clone(), definition.clone()));
+                }
+            }
+        }
+
+        if outdated.is_empty() {
+            return Ok(());
+        }
+
+        let mp = MultiProgress::new();
+        let total_pb = mp.add(ProgressBar::new(outdated.len() as u64));
+        total_pb.set_style(
+            ProgressStyle::default_bar()
+                .template("[{elapsed_precise}] {bar:40.green/blue} {pos:>7}/{len:7} {msg}")
+                .unwrap(),
+        );
+        total_pb.set_message("Resolving");
+
+        let tasks: Vec<_> = outdated
+            .into_iter()
+            .map(|(project_type, name, definition)| {
+                let client = client.clone();
+                let pb = mp.add(ProgressBar::new(1));
+                pb.set_style(
+                    ProgressStyle::default_bar()
+                        .template("[{elapsed_precise}] {bar:40.cyan/blue} {pos:>7}/{len:7} {msg}")
+                        .unwrap(),
+                );
+                pb.set_message(format!("Processing {}", name));
+
+                let total_pb = total_pb.clone();
+                let strategy = manifest.strategy(&definition);
+                let minecraft = manifest.enviroment.minecraft.clone();
+                let loaders = manifest.enviroment.loaders.clone();
+
+                task::spawn(async move {
+                    let Definition { version, side, .. } = definition;
+                    let version = client
+                        .get_version(&name, &minecraft, &loaders, &version, &strategy)
+                        .await?;
+                    let package = Package::new(version, side)?;
+
+                    pb.inc(1);
+                    pb.finish_and_clear();
+                    total_pb.inc(1);
+                    anyhow::Ok((project_type, name, package))
+                })
+            })
+            .collect();
+
+        let results = try_join_all(tasks).await?;
+
+        total_pb.finish_and_clear();
+        mp.clear().unwrap();
+
+        for result in results {
+            let (project_type, name, package) = result?;
+            self.section_mut(project_type).insert(name, package);
+        }
+
+        Ok(())
+    }
+
+    /// Iterates over every locked package across all sections
+    pub fn packages(&self) -> impl Iterator<Item = (ProjectType, &String, &Package)> {
+        ProjectType::ALL.into_iter().flat_map(|project_type| {
+            self.section(project_type)
+                .iter()
+                .map(move |(name, package)| (project_type, name, package))
+        })
+    }
+
+    /// Builds the mrpack file list purely from the locked packages.
+    pub fn mrpack_files(&self) -> Vec<mrpack::File> {
+        self.packages()
+            .map(|(project_type, _, package)| package.to_mrpack(project_type))
+            .collect()
+    }
+}
+
+/// Makes sure a filename can't place its package outside of the project type's directory
+fn check_filename(filename: &str) -> Result<()> {
+    let path = Path::new(filename);
+
+    if sanitize(path).is_err() || path.components().count() != 1 {
+        bail!("Refusing to lock {filename}, it isn't a plain file name");
+    }
+
+    Ok(())
+}
+
+impl Package {
+    /// Locks the primary file of a Modrinth version.
+    pub fn new(version: Version, side: Side) -> Result<Self> {
+        let Version {
+            id,
+            project_id,
+            version_number,
+            files,
+            ..
+        } = version;
+
+        // Modrinth treats the first file as the primary one when none is explicitly marked
+        let primary = files.iter().position(|file| file.primary).unwrap_or(0);
+        let file = files
+            .into_iter()
+            .nth(primary)
+            .ok_or_else(|| anyhow!("Version {version_number} has no files"))?;
+
+        let hash = |algorithm: &str| {
+            file.hashes
+                .get(algorithm)
+                .cloned()
+                .ok_or_else(|| anyhow!("File {} is missing its {algorithm} hash", file.filename))
+        };
+
+        check_filename(&file.filename)?;
+
+        Ok(Self {
+            project_id,
+            version_id: id,
+            version: version_number,
+            side,
+            sha1: hash("sha1")?,
+            sha512: hash("sha512")?,
+            filename: file.filename,
+            url: file.url,
+            size: file.size,
+        })
+    }
+
+    /// Whether this package still matches what the manifest asks for.
+    pub fn satisfies(&self, definition: &Definition, minecraft: &str) -> bool {
+        definition.version.matches(&self.version, minecraft) && self.side == definition.side
+    }
+
+    /// Where this package goes relative to the game directory
+    pub fn path(&self, project_type: ProjectType) -> PathBuf {
+        Path::new(project_type.as_directory()).join(&self.filename)
+    }
+
+    pub fn to_mrpack(&self, project_type: ProjectType) -> mrpack::File {
+        mrpack::File {
+            path: self.path(project_type),
+            hashes: HashMap::from([
+                ("sha1".to_string(), self.sha1.clone()),
+                ("sha512".to_string(), self.sha512.clone()),
+            ]),
+            env: Some(self.side.clone().into()),
+            downloads: vec![self.url.clone()],
+            file_size: self.size,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::fake::{Fake, version};
+
+    const MANIFEST: &str = r#"
+        [pack]
+        name = "test"
+        version = "1.0.0"
+
+        [enviroment]
+        minecraft = "1.21.1"
+        fabric = "0.16.10"
+
+        [mods]
+        sodium = { version = "latest", side = "both" }
+        iris = { version = "latest", side = "both" }
+    "#;
+
+    #[tokio::test]
+    async fn resolve_keeps_entries_that_still_match() {
+        let manifest: Manifest = toml_edit::de::from_str(MANIFEST).unwrap();
+        let client = Fake::default()
+            .project("AAA", "sodium")
+            .project("CCC", "iris")
+            .version(version("s1", "AAA", "0.6.0", 0))
+            .version(version("s2", "AAA", "0.6.5", 1))
+            .version(version("i1", "CCC", "1.8.0", 0));
+
+        let mut lockfile = Lockfile::default();
+        lockfile.retarget(&manifest.enviroment);
+        lockfile.mods.insert(
+            "sodium".to_string(),
+            Package::new(version("s1", "AAA", "0.6.0", 0), Side::Both).unwrap(),
+        );
+        lockfile.mods.insert(
+            "removed".to_string(),
+            Package::new(version("r1", "RRR", "1.0.0", 0), Side::Both).unwrap(),
+        );
+
+        lockfile.resolve(&client, &manifest).await.unwrap();
+
+        assert_eq!(client.lookups(), 1);
+        assert_eq!(lockfile.mods["sodium"].version_id, "s1");
+        assert_eq!(lockfile.mods["iris"].version_id, "i1");
+        assert!(!lockfile.mods.contains_key("removed"));
+
+        // Another game version invalidates everything that was locked
+        let mut manifest = manifest;
+        manifest.enviroment.minecraft = "1.21.4".to_string();
+        assert!(lockfile.resolve(&client, &manifest).await.is_err());
+        assert_eq!(lockfile.minecraft, "1.21.4");
+        assert!(lockfile.mods.is_empty());
+    }
+
+    #[test]
+    fn filenames_must_be_plain() {
+        assert!(check_filename("sodium-0.6.5.jar").is_ok());
+
+        for filename in ["", "..", "../sodium.jar", "mods/sodium.jar", "/sodium.jar"] {
+            assert!(check_filename(filename).is_err(), "{filename}");
+        }
+    }
+}
@@ -1,20 +1,40 @@
-use std::{env::current_dir, fmt::Display, path::PathBuf, str::FromStr};
+use std::{
+    env::{self, current_dir},
+    fmt::Display,
+    path::PathBuf,
+    str::FromStr,
+};
 
 use anyhow::Result;
 use clap::{Parser, Subcommand};
-use manifest::Loader;
+use commands::ExportFormat;
+use config::Config;
+use manifest::{Loader, Strategy};
 use modrinth::Client;
 
+mod cache;
 mod commands;
+mod config;
+mod conflicts;
+mod constraint;
+mod curseforge;
+mod http;
+mod install;
+mod loaders;
 mod lockfile;
 mod manifest;
 mod modrinth;
 mod mrpack;
+mod ratelimit;
+mod registry;
 
 /// Podzol - A modpack package manager
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
 struct Args {
+    /// Base url of the Modrinth API (defaults to the manifest's or the public API)
+    #[arg(long, global = true, env = "PODZOL_MODRINTH_URL")]
+    modrinth_url: Option<String>,
     #[command(subcommand)]
     command: Commands,
 }
@@ -33,7 +53,7 @@ enum Commands {
         version: String,
         #[arg(short, long)]
         game_version: Option<String>,
-        /// A compatible loader
+        /// The mod loader to use, at its latest stable version for the minecraft version
         #[arg(short, long)]
         loader: Option<Loader>,
         #[arg(long, default_value = "false")]
@@ -45,14 +65,141 @@ enum Commands {
         projects: Vec<String>,
         #[arg(long = "type", short = 't', default_value = "mod")]
         project_type: ProjectType,
+        /// How to pick the version of these projects (release, beta, alpha or featured)
+        #[arg(long, short)]
+        strategy: Option<Strategy>,
+        /// Add optional dependencies without asking
+        #[arg(long, conflicts_with = "no_optional")]
+        optional: bool,
+        /// Skip optional dependencies without asking, the default outside of a terminal
+        #[arg(long)]
+        no_optional: bool,
     },
-    /// Remove a mod from the manifest
-    Remove,
+    /// Search Modrinth for projects compatible with the pack
+    Search {
+        /// What to search for (defaults to the most relevant projects)
+        query: Vec<String>,
+        #[arg(long = "type", short = 't', default_value = "mod")]
+        project_type: ProjectType,
+        /// How many results to show
+        #[arg(long, short, default_value = "10")]
+        limit: usize,
+        /// Pick results to add to the pack
+        #[arg(long, short, default_value = "false")]
+        interactive: bool,
+    },
+    /// Remove a project from the manifest
+    Remove {
+        #[arg(required = true, num_args = 1..)]
+        projects: Vec<String>,
+        #[arg(long = "type", short = 't', default_value = "mod")]
+        project_type: ProjectType,
+    },
+    /// Update projects to their newest compatible version
+    Update {
+        /// The projects to update (defaults to all of them)
+        projects: Vec<String>,
+        /// Only print what would change
+        #[arg(long, default_value = "false")]
+        dry_run: bool,
+    },
+    /// List projects that have a newer compatible version
+    Outdated {
+        /// Print the changelog of every newer version
+        #[arg(long, default_value = "false")]
+        changelog: bool,
+    },
+    /// Validates the manifest and checks the pack for incompatible projects
+    Check,
     /// Exports the project
-    Export,
+    Export {
+        /// The format to export to (mrpack or curseforge)
+        #[arg(long, short, default_value = "mrpack")]
+        format: ExportFormat,
+        /// Set up a ready to run server in this directory instead
+        #[arg(long, conflicts_with = "format")]
+        server: Option<PathBuf>,
+    },
+    /// Install the pack into a Prism Launcher or MultiMC instance
+    Install {
+        /// Path to the instance directory, created if it doesn't exist
+        #[arg(long)]
+        instance: PathBuf,
+    },
+    /// Port the pack to another minecraft version
+    Migrate {
+        /// The minecraft version to migrate to
+        minecraft: String,
+        /// Don't ask for confirmation
+        #[arg(long, short, default_value = "false")]
+        yes: bool,
+    },
+    /// Show which minecraft versions every project of the pack is available for
+    Matrix {
+        /// Include snapshots
+        #[arg(long, default_value = "false")]
+        snapshots: bool,
+        /// How many of the newest minecraft versions to check
+        #[arg(long, short, default_value = "10")]
+        limit: usize,
+    },
+    /// Inspect and change the pack's loader versions
+    Loader {
+        #[command(subcommand)]
+        command: LoaderCommand,
+    },
+    /// Manage the shared download cache
+    Cache {
+        #[command(subcommand)]
+        command: CacheCommand,
+    },
+    /// Create a new podzol project from an existing modpack
+    Import {
+        /// The modpack to import: an mrpack, a packwiz directory or a CurseForge zip
+        source: PathBuf,
+        /// Path to the directory to create the project in (defaults to current directory)
+        path: Option<PathBuf>,
+    },
 }
 
-#[derive(Clone)]
+impl Commands {
+    /// Whether the command works on the pack in the current directory
+    const fn uses_pack(&self) -> bool {
+        !matches!(
+            self,
+            Self::Init { .. } | Self::Import { .. } | Self::Cache { .. }
+        )
+    }
+}
+
+#[derive(Subcommand)]
+enum CacheCommand {
+    /// List the cached files
+    List,
+    /// Show how much space the cache takes up
+    Size,
+    /// Remove every cached file
+    Clean,
+    /// Remove cached files no known lockfile references anymore
+    Gc,
+}
+
+#[derive(Subcommand)]
+enum LoaderCommand {
+    /// Show the pack's loaders and whether a newer version is out
+    Show,
+    /// List the versions of a loader that support the pack's minecraft version
+    List {
+        /// The loader to list versions of (defaults to the pack's)
+        loader: Option<Loader>,
+    },
+    /// Switch a loader to a specific version
+    Set { loader: Loader, version: String },
+    /// Upgrade the pack's loaders to their latest stable version
+    Upgrade,
+}
+
+#[derive(Clone, Copy)]
 enum ProjectType {
     Mod,
     ResourcePack,
@@ -60,6 +207,8 @@ enum ProjectType {
 }
 
 impl ProjectType {
+    pub const ALL: [Self; 3] = [Self::Mod, Self::ResourcePack, Self::Shader];
+
     pub const fn as_table(&self) -> &'static str {
         match self {
             Self::Mod => "mods",
@@ -67,6 +216,34 @@ impl ProjectType {
             Self::Shader => "shaders",
         }
     }
+
+    /// Maps a Modrinth `project_type` onto the matching manifest table
+    pub fn from_modrinth(project_type: &str) -> Option<Self> {
+        match project_type {
+            "mod" => Some(Self::Mod),
+            "resourcepack" => Some(Self::ResourcePack),
+            "shader" => Some(Self::Shader),
+            _ => None,
+        }
+    }
+
+    /// The `project_type` Modrinth uses for this kind of project
+    pub const fn as_modrinth(&self) -> &'static str {
+        match self {
+            Self::Mod => "mod",
+            Self::ResourcePack => "resourcepack",
+            Self::Shader => "shader",
+        }
+    }
+
+    /// The directory this kind of project is installed into
+    pub const fn as_directory(&self) -> &'static str {
+        match self {
+            Self::Mod => "mods",
+            Self::ResourcePack => "resourcepacks",
+            Self::Shader => "shaderpacks",
+        }
+    }
 }
 
 impl FromStr for ProjectType {
@@ -98,19 +275,91 @@ impl Display for ProjectType {
 async fn main() -> Result<()> {
     tracing_subscriber::fmt::init();
 
-    let Args { command } = Args::parse();
+    let Args {
+        modrinth_url,
+        command,
+    } = Args::parse();
+
+    let modrinth_url = match modrinth_url {
+        Some(url) => Some(url),
+        None if command.uses_pack() => match manifest::modrinth_url("podzol.toml") {
+            Ok(url) => url,
+            // check points at the broken manifest itself
+            Err(_) if matches!(command, Commands::Check) => None,
+            Err(err) => {
+                eprintln!("Warning: ignoring the Modrinth url in podzol.toml: {err}");
+                None
+            }
+        },
+        None => None,
+    }
+    .unwrap_or_else(|| modrinth::DEFAULT_URL.to_string());
+    let token = match env::var("MODRINTH_TOKEN") {
+        Ok(token) => Some(token),
+        Err(_) => Config::read()?.modrinth.token,
+    }
+    .filter(|token| !token.is_empty());
 
-    let client = Client::new()?;
+    let client = Client::new(&modrinth_url, token)?;
 
     match command {
         Commands::Add {
             projects,
             project_type,
+            strategy,
+            optional,
+            no_optional,
         } => {
-            commands::add(&client, projects, project_type).await?;
+            let optional = match (optional, no_optional) {
+                (true, _) => Some(true),
+                (_, true) => Some(false),
+                _ => None,
+            };
+
+            commands::add(&client, projects, project_type, strategy, optional).await?;
+        }
+        Commands::Search {
+            query,
+            project_type,
+            limit,
+            interactive,
+        } => {
+            commands::search(&client, &query.join(" "), project_type, limit, interactive).await?;
+        }
+        Commands::Update { projects, dry_run } => {
+            commands::update(&client, projects, dry_run).await?;
+        }
+        Commands::Outdated { changelog } => {
+            commands::outdated(&client, changelog).await?;
+        }
+        Commands::Check => {
+            commands::check(&client).await?;
+        }
+        Commands::Export { format, server } => {
+            commands::export(&client, format, server).await?;
+        }
+        Commands::Migrate { minecraft, yes } => {
+            commands::migrate(&client, minecraft, yes).await?;
         }
-        Commands::Export => {
-            commands::export(&client).await?;
+        Commands::Matrix { snapshots, limit } => {
+            commands::matrix(&client, snapshots, limit).await?;
+        }
+        Commands::Loader { command } => {
+            commands::loader(command).await?;
+        }
+        Commands::Cache { command } => {
+            commands::cache(command)?;
+        }
+        Commands::Install { instance } => {
+            commands::install(&client, &instance).await?;
+        }
+        Commands::Import { source, path } => {
+            commands::import(
+                &client,
+                &source,
+                &path.unwrap_or_else(|| current_dir().expect("Failed to fetch current dir")),
+            )
+            .await?;
         }
         Commands::Init {
             path,
@@ -118,7 +367,7 @@ async fn main() -> Result<()> {
             name,
             no_interactive,
             game_version,
-            ..
+            loader,
         } => {
             if no_interactive {
                 commands::init(
@@ -127,13 +376,19 @@ async fn main() -> Result<()> {
                     version,
                     game_version,
                     name,
+                    loader,
                 )
                 .await?;
             } else {
-                commands::init_interactive(&client).await?;
+                commands::init_interactive(&client, loader).await?;
             }
         }
-        Commands::Remove => todo!(),
+        Commands::Remove {
+            projects,
+            project_type,
+        } => {
+            commands::remove(projects, project_type)?;
+        }
     }
 
     Ok(())
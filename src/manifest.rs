@@ -1,16 +1,24 @@
 use anyhow::Result;
 use async_zip::{Compression, ZipEntryBuilder, base::write::ZipFileWriter};
 use futures_io::AsyncWrite;
-use futures_util::future::try_join_all;
-use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
 use serde::{Deserialize, Serialize};
 use serde_with::{DeserializeFromStr, SerializeDisplay};
-use std::{collections::HashMap, fmt::Display, fs, path::Path, str::FromStr};
-use tokio::task;
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 
 use crate::{
-    modrinth::Client,
-    mrpack::{self, Game, Metadata},
+    ProjectType,
+    constraint::VersionReq,
+    curseforge,
+    lockfile::Lockfile,
+    modrinth::{Version, VersionType},
+    mrpack::{Game, Metadata},
 };
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -18,6 +26,8 @@ use crate::{
 pub struct Manifest {
     pub pack: Pack,
     pub enviroment: Enviroment,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modrinth: Option<ModrinthConfig>,
     #[serde(default, skip_serializing_if = "HashMap::is_empty")]
     pub files: HashMap<FileLocation, Vec<String>>,
     #[serde(default, skip_serializing_if = "HashMap::is_empty")]
@@ -48,6 +58,83 @@ impl FileLocation {
     }
 }
 
+/// A single file matched by the `files` globs
+#[derive(Debug)]
+pub struct Override {
+    pub location: FileLocation,
+    /// Where the file lives in the project
+    pub source: PathBuf,
+    /// Where the file goes relative to the game directory
+    pub target: PathBuf,
+}
+
+/// Escapes text for use in HTML content and attribute values
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+/// The literal directory a glob pattern starts from.
+///
+/// `config/*` and `config/sodium.json` both resolve to `config`, so matched files land at the
+/// root of the overrides, while `overrides/**/*` keeps everything below `overrides` nested.
+fn glob_base(pattern: &str) -> PathBuf {
+    let path = Path::new(pattern);
+
+    let literal: PathBuf = path
+        .components()
+        .take_while(|component| {
+            !component
+                .as_os_str()
+                .to_string_lossy()
+                .contains(['*', '?', '['])
+        })
+        .collect();
+
+    if literal == path {
+        return path.parent().map(Path::to_path_buf).unwrap_or_default();
+    }
+
+    literal
+}
+
+impl FileLocation {
+    /// The files a single `files` glob puts into this location
+    pub fn expand(&self, pattern: &str) -> Result<Vec<Override>> {
+        let base = glob_base(pattern);
+        let mut overrides = Vec::new();
+
+        for entry in glob::glob(pattern)? {
+            let source = entry?;
+
+            if source.is_dir() {
+                continue;
+            }
+
+            let target = source.strip_prefix(&base)?.to_path_buf();
+
+            overrides.push(Override {
+                location: self.clone(),
+                source,
+                target,
+            });
+        }
+
+        Ok(overrides)
+    }
+}
+
 impl FromStr for FileLocation {
     type Err = String;
 
@@ -74,140 +161,73 @@ impl Display for FileLocation {
 }
 
 impl Manifest {
-    pub async fn build_mrpack<W: AsyncWrite + Unpin>(
-        self,
-        client: &Client,
-        writer: &mut ZipFileWriter<W>,
-    ) -> Result<()> {
-        async fn process_items<P: AsRef<Path> + Send + 'static>(
-            client: Client,
-            items: HashMap<String, Definition>,
-            minecraft: &str,
-            loaders: &HashMap<Loader, String>,
-            path: P,
-            mp: MultiProgress,
-            total_pb: ProgressBar,
-        ) -> Result<Vec<mrpack::File>> {
-            let tasks: Vec<_> = items
-                .into_iter()
-                .map(|(name, definition)| {
-                    let client = client.clone();
-                    let path = path.as_ref().to_owned();
-                    let pb = mp.add(ProgressBar::new(1));
-                    pb.set_style(
-                        ProgressStyle::default_bar()
-                            .template(
-                                "[{elapsed_precise}] {bar:40.cyan/blue} {pos:>7}/{len:7} {msg}",
-                            )
-                            .unwrap(),
-                    );
-                    pb.set_message(format!("Processing {}", name));
-
-                    let total_pb = total_pb.clone();
-                    let minecraft = minecraft.to_string();
-                    let loaders = loaders.clone();
-
-                    task::spawn(async move {
-                        let Definition { version, side } = definition;
-                        let version = client
-                            .get_version(&name, &minecraft, &loaders, &version)
-                            .await?;
-                        let mut files = Vec::new();
-
-                        for file in version.files {
-                            if !file.primary {
-                                continue;
-                            }
-
-                            files.push(mrpack::File {
-                                path: path.join(&file.filename),
-                                hashes: file.hashes,
-                                env: Some(side.clone().into()),
-                                downloads: vec![file.url],
-                                file_size: file.size,
-                            });
-                        }
-
-                        pb.inc(1);
-                        pb.finish_and_clear();
-                        total_pb.inc(1);
-                        anyhow::Ok(files)
-                    })
-                })
-                .collect();
+    pub const fn section(&self, project_type: ProjectType) -> &HashMap<String, Definition> {
+        match project_type {
+            ProjectType::Mod => &self.mods,
+            ProjectType::ResourcePack => &self.resource_packs,
+            ProjectType::Shader => &self.shaders,
+        }
+    }
 
-            let results = try_join_all(tasks).await?;
-            Ok(results.into_iter().flatten().flatten().collect())
+    pub const fn section_mut(
+        &mut self,
+        project_type: ProjectType,
+    ) -> &mut HashMap<String, Definition> {
+        match project_type {
+            ProjectType::Mod => &mut self.mods,
+            ProjectType::ResourcePack => &mut self.resource_packs,
+            ProjectType::Shader => &mut self.shaders,
         }
+    }
 
-        let total_items = self.mods.len() + self.resource_packs.len() + self.shaders.len();
-        let mp = MultiProgress::new();
-        let total_pb = mp.add(ProgressBar::new(total_items as u64));
-        total_pb.set_style(
-            ProgressStyle::default_bar()
-                .template("[{elapsed_precise}] {bar:40.green/blue} {pos:>7}/{len:7} {msg}")
-                .unwrap(),
-        );
-        total_pb.set_message("Total progress");
-
-        let (mods, resource_packs, shaders) = tokio::try_join!(
-            process_items(
-                client.clone(),
-                self.mods,
-                &self.enviroment.minecraft,
-                &self.enviroment.loaders,
-                "mods",
-                mp.clone(),
-                total_pb.clone()
-            ),
-            process_items(
-                client.clone(),
-                self.resource_packs,
-                &self.enviroment.minecraft,
-                &self.enviroment.loaders,
-                "resourcepacks",
-                mp.clone(),
-                total_pb.clone()
-            ),
-            process_items(
-                client.clone(),
-                self.shaders,
-                &self.enviroment.minecraft,
-                &self.enviroment.loaders,
-                "shaderpacks",
-                mp.clone(),
-                total_pb.clone()
-            ),
-        )?;
+    /// The strategy used to pick versions of `definition`, falling back to the pack wide one
+    pub fn strategy(&self, definition: &Definition) -> Strategy {
+        definition
+            .strategy
+            .clone()
+            .or_else(|| self.pack.strategy.clone())
+            .unwrap_or_default()
+    }
 
-        for (location, patterns) in self.files {
+    /// Expands the `files` globs into the files that end up in the override directories
+    pub fn overrides(&self) -> Result<Vec<Override>> {
+        let mut overrides = Vec::new();
+
+        for (location, patterns) in &self.files {
             for pattern in patterns {
-                for entry in glob::glob(&pattern)? {
-                    let path = entry?;
-
-                    let entry = ZipEntryBuilder::new(
-                        location
-                            .as_ovveride()
-                            .join(path.strip_prefix(path.parent().unwrap())?)
-                            .display()
-                            .to_string()
-                            .into(), // This is fine... right?
-                        Compression::Deflate,
-                    );
-                    let data = fs::read(path)?;
-
-                    writer.write_entry_whole(entry, &data).await?;
-                }
+                overrides.extend(location.expand(pattern)?);
             }
         }
 
-        let mut files = Vec::with_capacity(total_items);
-        files.extend(mods);
-        files.extend(resource_packs);
-        files.extend(shaders);
+        Ok(overrides)
+    }
 
-        total_pb.finish_and_clear();
-        mp.clear().unwrap();
+    pub async fn build_mrpack<W: AsyncWrite + Unpin>(
+        self,
+        lockfile: &Lockfile,
+        writer: &mut ZipFileWriter<W>,
+    ) -> Result<()> {
+        for Override {
+            location,
+            source,
+            target,
+        } in self.overrides()?
+        {
+            let entry = ZipEntryBuilder::new(
+                location
+                    .as_ovveride()
+                    .join(target)
+                    .display()
+                    .to_string()
+                    .into(), // This is fine... right?
+                Compression::Deflate,
+            );
+            let data = fs::read(source)?;
+
+            writer.write_entry_whole(entry, &data).await?;
+        }
+
+        let files = lockfile.mrpack_files();
 
         let dependencies: HashMap<String, String> = self
             .enviroment
@@ -240,6 +260,88 @@ impl Manifest {
 
         Ok(())
     }
+
+    /// Writes a CurseForge modpack made of the given, already mapped, files
+    pub async fn build_curseforge<W: AsyncWrite + Unpin>(
+        self,
+        files: Vec<(String, CurseForgeFile)>,
+        writer: &mut ZipFileWriter<W>,
+    ) -> Result<()> {
+        // CurseForge packs only have a single override directory which is installed on clients
+        for Override {
+            location,
+            source,
+            target,
+        } in self.overrides()?
+        {
+            if location == FileLocation::Server {
+                continue;
+            }
+
+            let entry = ZipEntryBuilder::new(
+                Path::new("overrides")
+                    .join(target)
+                    .display()
+                    .to_string()
+                    .into(),
+                Compression::Deflate,
+            );
+            let data = fs::read(source)?;
+
+            writer.write_entry_whole(entry, &data).await?;
+        }
+
+        let mut modlist = String::from("<ul>\n");
+        for (name, file) in &files {
+            modlist.push_str(&format!(
+                "<li><a href=\"https://www.curseforge.com/projects/{}\">{}</a></li>\n",
+                file.project,
+                escape_html(name)
+            ));
+        }
+        modlist.push_str("</ul>\n");
+
+        let mut loaders: Vec<_> = self.enviroment.loaders.into_iter().collect();
+        loaders.sort();
+
+        let manifest = curseforge::Manifest {
+            minecraft: curseforge::Minecraft {
+                version: self.enviroment.minecraft,
+                mod_loaders: loaders
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, (loader, version))| curseforge::ModLoader {
+                        id: format!("{loader}-{version}"),
+                        primary: i == 0,
+                    })
+                    .collect(),
+            },
+            manifest_type: "minecraftModpack".to_string(),
+            manifest_version: 1,
+            name: self.pack.name,
+            version: self.pack.version,
+            author: String::new(),
+            files: files
+                .into_iter()
+                .map(|(_, file)| curseforge::ManifestFile {
+                    project_id: file.project,
+                    file_id: file.file,
+                    required: true,
+                })
+                .collect(),
+            overrides: "overrides".to_string(),
+        };
+
+        let entry = ZipEntryBuilder::new("manifest.json".to_string().into(), Compression::Deflate);
+        writer
+            .write_entry_whole(entry, &serde_json::to_vec_pretty(&manifest)?)
+            .await?;
+
+        let entry = ZipEntryBuilder::new("modlist.html".to_string().into(), Compression::Deflate);
+        writer.write_entry_whole(entry, modlist.as_bytes()).await?;
+
+        Ok(())
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -247,6 +349,35 @@ pub struct Pack {
     pub name: String,
     pub version: String,
     pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strategy: Option<Strategy>,
+}
+
+/// Where the pack's projects are resolved from, for packs that don't use the public API
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ModrinthConfig {
+    pub url: String,
+}
+
+/// The Modrinth API url configured by the manifest at `path`, if it exists.
+///
+/// Only the `[modrinth]` table is read so commands keep working on manifests that
+/// are otherwise incomplete.
+pub fn modrinth_url(path: impl AsRef<Path>) -> Result<Option<String>> {
+    #[derive(Deserialize)]
+    struct Partial {
+        modrinth: Option<ModrinthConfig>,
+    }
+
+    let data = match fs::read(path) {
+        Ok(data) => data,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+
+    let partial: Partial = toml_edit::de::from_slice(&data)?;
+
+    Ok(partial.modrinth.map(|modrinth| modrinth.url))
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -294,6 +425,8 @@ impl Display for Loader {
 }
 
 impl Loader {
+    pub const ALL: [Self; 4] = [Self::Fabric, Self::Forge, Self::Quilt, Self::NeoForge];
+
     pub const fn as_mrpack(&self) -> &'static str {
         match self {
             Self::Fabric => "fabric-loader",
@@ -302,12 +435,124 @@ impl Loader {
             Self::NeoForge => "neoforge",
         }
     }
+
+    /// The component uid Prism Launcher and MultiMC use for this loader
+    pub const fn as_component(&self) -> &'static str {
+        match self {
+            Self::Fabric => "net.fabricmc.fabric-loader",
+            Self::Forge => "net.minecraftforge",
+            Self::Quilt => "org.quiltmc.quilt-loader",
+            Self::NeoForge => "net.neoforged",
+        }
+    }
+
+    pub fn from_mrpack(dependency: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|loader| loader.as_mrpack() == dependency)
+    }
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Definition {
-    version: String,
-    side: Side,
+    pub version: VersionReq,
+    pub side: Side,
+    /// Whether this project was pulled in as a dependency of another one
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub dependency: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strategy: Option<Strategy>,
+    /// The matching CurseForge file, used instead of a fingerprint lookup when exporting
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub curseforge: Option<CurseForgeFile>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct CurseForgeFile {
+    pub project: u64,
+    pub file: u64,
+    /// The Modrinth version ID the file was matched to, the pin no longer applies once the
+    /// project resolves to a different version
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+}
+
+/// How a version is picked out of the ones compatible with the pack
+#[derive(
+    Debug,
+    Default,
+    DeserializeFromStr,
+    SerializeDisplay,
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+)]
+pub enum Strategy {
+    /// Only stable releases
+    #[default]
+    Release,
+    /// Releases and betas
+    Beta,
+    /// Anything, including alphas
+    Alpha,
+    /// Featured versions, falling back to stable releases
+    Featured,
+}
+
+impl Strategy {
+    pub fn allows(&self, version: &Version) -> bool {
+        match self {
+            Self::Release => version.version_type == VersionType::Release,
+            Self::Beta => matches!(
+                version.version_type,
+                VersionType::Release | VersionType::Beta
+            ),
+            Self::Alpha => true,
+            Self::Featured => version.featured || version.version_type == VersionType::Release,
+        }
+    }
+
+    /// Picks the most recently published version allowed by this strategy
+    pub fn select(&self, versions: Vec<Version>) -> Option<Version> {
+        let prefer_featured =
+            *self == Self::Featured && versions.iter().any(|version| version.featured);
+
+        versions
+            .into_iter()
+            .filter(|version| self.allows(version))
+            .filter(|version| !prefer_featured || version.featured)
+            .max_by_key(|version| version.date_published)
+    }
+}
+
+impl FromStr for Strategy {
+    type Err = String;
+
+    fn from_str(strategy: &str) -> Result<Self, Self::Err> {
+        match strategy {
+            "release" => Ok(Self::Release),
+            "beta" => Ok(Self::Beta),
+            "alpha" => Ok(Self::Alpha),
+            "featured" => Ok(Self::Featured),
+            _ => Err(format!(
+                "Unknown strategy '{strategy}'. Supported strategies are: release, beta, alpha, featured",
+            )),
+        }
+    }
+}
+
+impl Display for Strategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Release => write!(f, "release"),
+            Self::Beta => write!(f, "beta"),
+            Self::Alpha => write!(f, "alpha"),
+            Self::Featured => write!(f, "featured"),
+        }
+    }
 }
 
 #[derive(
@@ -344,3 +589,79 @@ impl Display for Side {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::fake::version;
+
+    fn versions() -> Vec<Version> {
+        let mut beta = version("b", "AAA", "0.7.0-beta.1", 2);
+        beta.version_type = VersionType::Beta;
+        let mut alpha = version("a", "AAA", "0.8.0-alpha.1", 3);
+        alpha.version_type = VersionType::Alpha;
+
+        // Listed out of order, only the publishing date counts
+        vec![
+            version("r2", "AAA", "0.6.5", 1),
+            alpha,
+            version("r1", "AAA", "0.6.0", 0),
+            beta,
+        ]
+    }
+
+    fn select(strategy: Strategy, versions: Vec<Version>) -> Option<String> {
+        strategy.select(versions).map(|version| version.id)
+    }
+
+    #[test]
+    fn strategies_filter_by_version_type() {
+        assert_eq!(select(Strategy::Release, versions()).as_deref(), Some("r2"));
+        assert_eq!(select(Strategy::Beta, versions()).as_deref(), Some("b"));
+        assert_eq!(select(Strategy::Alpha, versions()).as_deref(), Some("a"));
+    }
+
+    #[test]
+    fn featured_falls_back_to_releases() {
+        assert_eq!(
+            select(Strategy::Featured, versions()).as_deref(),
+            Some("r2")
+        );
+
+        let mut versions = versions();
+        versions[3].featured = true;
+        versions[2].featured = true;
+        assert_eq!(select(Strategy::Featured, versions).as_deref(), Some("b"));
+    }
+
+    #[test]
+    fn newest_version_wins() {
+        let mut versions = versions();
+        versions.retain(|version| version.id.starts_with('r'));
+        assert_eq!(
+            select(Strategy::Release, versions.clone()).as_deref(),
+            Some("r2")
+        );
+
+        versions.reverse();
+        assert_eq!(select(Strategy::Release, versions).as_deref(), Some("r2"));
+
+        assert_eq!(select(Strategy::Release, Vec::new()), None);
+    }
+
+    #[test]
+    fn glob_base_is_the_literal_prefix() {
+        let cases = [
+            ("config/*", "config"),
+            ("config/sodium.json", "config"),
+            ("sodium.json", ""),
+            ("overrides/**/*", "overrides"),
+            ("*.txt", ""),
+            ("**/*", ""),
+        ];
+
+        for (pattern, base) in cases {
+            assert_eq!(glob_base(pattern), Path::new(base), "{pattern}");
+        }
+    }
+}
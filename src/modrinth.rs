@@ -1,26 +1,87 @@
-use std::{collections::HashMap, fmt::Display, str::FromStr, sync::Arc};
+use std::{collections::HashMap, fmt::Display, str::FromStr};
 
-use anyhow::Result;
+use anyhow::{Result, anyhow};
 use chrono::{DateTime, Utc};
+use futures_util::future::try_join_all;
 use itertools::Itertools;
-use reqwest::Client as HttpClient;
-use rustls::crypto::aws_lc_rs;
-use rustls_platform_verifier::BuilderVerifierExt;
-use serde::{Deserialize, Serialize};
+use reqwest::{Client as HttpClient, RequestBuilder, Response, StatusCode, header::AUTHORIZATION};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
 use serde_with::{DeserializeFromStr, SerializeDisplay};
+use sha2::{Digest, Sha512};
 
 use crate::{
+    ProjectType,
+    cache::Cache,
+    http,
     manifest::{Loader, Side},
     mrpack::Requirement,
+    ratelimit::RateLimiter,
+    registry::Registry,
 };
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Version {
+    pub id: String,
+    pub project_id: String,
     pub version_number: String,
+    pub version_type: VersionType,
+    pub date_published: DateTime<Utc>,
+    #[serde(default)]
+    pub featured: bool,
+    pub changelog: Option<String>,
+    #[serde(default)]
+    pub game_versions: Vec<String>,
     pub files: Vec<File>,
+    #[serde(default)]
+    pub dependencies: Vec<Dependency>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Dependency {
+    pub version_id: Option<String>,
+    pub project_id: Option<String>,
+    pub file_name: Option<String>,
+    pub dependency_type: DependencyType,
+}
+
+#[derive(
+    Debug, DeserializeFromStr, SerializeDisplay, Clone, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
+pub enum DependencyType {
+    Required,
+    Optional,
+    Incompatible,
+    Embedded,
+}
+
+impl FromStr for DependencyType {
+    type Err = String;
+
+    fn from_str(t: &str) -> Result<Self, Self::Err> {
+        match t {
+            "required" => Ok(Self::Required),
+            "optional" => Ok(Self::Optional),
+            "incompatible" => Ok(Self::Incompatible),
+            "embedded" => Ok(Self::Embedded),
+            _ => Err(format!(
+                "Unknown dependency type '{t}'. Supported types are: required, optional, incompatible, embedded",
+            )),
+        }
+    }
+}
+
+impl Display for DependencyType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Required => write!(f, "required"),
+            Self::Optional => write!(f, "optional"),
+            Self::Incompatible => write!(f, "incompatible"),
+            Self::Embedded => write!(f, "embedded"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
 
 pub struct File {
     pub hashes: HashMap<String, String>,
@@ -32,21 +93,46 @@ pub struct File {
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Project {
+    pub id: String,
+    pub slug: String,
+    pub project_type: String,
     pub client_side: Requirement,
     pub server_side: Requirement,
 }
 
 impl Project {
     pub fn get_side(&self) -> Side {
-        if self.client_side.is_needed() && !self.server_side.is_needed() {
-            return Side::Client;
-        }
+        side(&self.client_side, &self.server_side)
+    }
+}
 
-        if !self.client_side.is_needed() && self.server_side.is_needed() {
-            return Side::Server;
-        }
+#[derive(Debug, Deserialize)]
+pub struct SearchResults {
+    pub hits: Vec<SearchHit>,
+    pub total_hits: u64,
+}
 
-        Side::Both
+#[derive(Debug, Deserialize)]
+pub struct SearchHit {
+    pub slug: String,
+    pub title: String,
+    pub description: String,
+    pub downloads: u64,
+    pub client_side: Requirement,
+    pub server_side: Requirement,
+}
+
+impl SearchHit {
+    pub fn get_side(&self) -> Side {
+        side(&self.client_side, &self.server_side)
+    }
+}
+
+fn side(client_side: &Requirement, server_side: &Requirement) -> Side {
+    match (client_side.is_needed(), server_side.is_needed()) {
+        (true, false) => Side::Client,
+        (false, true) => Side::Server,
+        _ => Side::Both,
     }
 }
 
@@ -96,32 +182,136 @@ impl Display for VersionType {
     }
 }
 
+/// The lowercase hex sha512 hash Modrinth identifies files by
+pub fn sha512(data: &[u8]) -> String {
+    format!("{:x}", Sha512::digest(data))
+}
+
+pub const DEFAULT_URL: &str = "https://api.modrinth.com/v2";
+
+/// How many IDs are looked up per request to the bulk endpoints
+const BULK_CHUNK_SIZE: usize = 100;
+
 #[derive(Clone)]
 pub struct Client {
     http_client: HttpClient,
+    base_url: String,
+    token: Option<String>,
+    limiter: RateLimiter,
+    cache: Cache,
 }
 
 impl Client {
-    pub fn new() -> Result<Self> {
-        let http_client = HttpClient::builder()
-            .use_preconfigured_tls(
-                rustls::ClientConfig::builder_with_provider(
-                    Arc::new(aws_lc_rs::default_provider()),
-                )
-                .with_safe_default_protocol_versions()?
-                .with_platform_verifier()
-                .with_no_client_auth(),
+    /// Creates a client for the Modrinth API at `base_url`, such as [`DEFAULT_URL`], Modrinth's
+    /// staging API or a self-hosted Labrinth instance.
+    ///
+    /// The token, if any, is sent with every API request but never with downloads.
+    pub fn new(base_url: &str, token: Option<String>) -> Result<Self> {
+        let http_client = http::client()?;
+        let cache = Cache::new()?;
+
+        Ok(Self {
+            http_client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            token,
+            limiter: RateLimiter::default(),
+            cache,
+        })
+    }
+
+    fn get(&self, path: &str) -> RequestBuilder {
+        self.authorize(self.http_client.get(format!("{}{path}", self.base_url)))
+    }
+
+    fn post(&self, path: &str) -> RequestBuilder {
+        self.authorize(self.http_client.post(format!("{}{path}", self.base_url)))
+    }
+
+    fn authorize(&self, request: RequestBuilder) -> RequestBuilder {
+        match &self.token {
+            Some(token) => request.header(AUTHORIZATION, token),
+            None => request,
+        }
+    }
+
+    async fn project_versions(
+        &self,
+        project: &str,
+        minecraft: Option<&str>,
+        loaders: &HashMap<Loader, String>,
+    ) -> Result<Vec<Version>> {
+        let loaders = loaders
+            .iter()
+            .format_with(",", |(loader, _), f| f(&format_args!("\"{loader}\"")))
+            .to_string();
+
+        let mut query = vec![("loaders", format!("[\"minecraft\", {loaders}]"))];
+        if let Some(minecraft) = minecraft {
+            query.push(("game_versions", format!(r#"["{minecraft}"]"#)));
+        }
+
+        let res = self
+            .send(
+                self.get(&format!("/project/{project}/version"))
+                    .query(&query),
             )
-            .build()?;
+            .await?
+            .json()
+            .await?;
+
+        Ok(res)
+    }
+
+    async fn send(&self, request: RequestBuilder) -> Result<Response> {
+        self.limiter.send(request).await.map_err(|err| {
+            let status = err
+                .downcast_ref::<reqwest::Error>()
+                .and_then(reqwest::Error::status);
 
-        Ok(Self { http_client })
+            match (status, &self.token) {
+                (Some(StatusCode::UNAUTHORIZED), Some(_)) => err.context(
+                    "Modrinth rejected the API token, check MODRINTH_TOKEN or the podzol config file",
+                ),
+                (Some(StatusCode::NOT_FOUND), Some(_)) => {
+                    err.context("Not found on Modrinth, or not visible to the configured token")
+                }
+                (Some(StatusCode::UNAUTHORIZED | StatusCode::NOT_FOUND), None) => err.context(
+                    "Not found on Modrinth, private and draft projects require a token in MODRINTH_TOKEN or the podzol config file",
+                ),
+                _ => err,
+            }
+        })
     }
 
-    pub async fn get_game_versions(&self) -> Result<Vec<GameVersion>> {
+    /// Fetches everything with the given IDs from a bulk endpoint, split into requests
+    /// short enough for any server to accept
+    async fn get_bulk<T: DeserializeOwned + Send>(
+        &self,
+        path: &str,
+        ids: &[String],
+    ) -> Result<Vec<T>> {
+        let chunks = try_join_all(ids.chunks(BULK_CHUNK_SIZE).map(|ids| async move {
+            let res: Vec<T> = self
+                .send(
+                    self.get(path)
+                        .query(&[("ids", serde_json::to_string(ids)?)]),
+                )
+                .await?
+                .json()
+                .await?;
+
+            anyhow::Ok(res)
+        }))
+        .await?;
+
+        Ok(chunks.into_iter().flatten().collect())
+    }
+}
+
+impl Registry for Client {
+    async fn get_game_versions(&self) -> Result<Vec<GameVersion>> {
         let res = self
-            .http_client
-            .get("https://api.modrinth.com/v2/tag/game_version")
-            .send()
+            .send(self.get("/tag/game_version"))
             .await?
             .json()
             .await?;
@@ -129,26 +319,57 @@ impl Client {
         Ok(res)
     }
 
-    pub async fn get_project_versions(
+    async fn get_projects(&self, ids: &[String]) -> Result<Vec<Project>> {
+        self.get_bulk("/projects", ids).await
+    }
+
+    async fn get_project_versions(
         &self,
         project: &str,
         minecraft: &str,
         loaders: &HashMap<Loader, String>,
     ) -> Result<Vec<Version>> {
-        let loaders = loaders
-            .iter()
-            .format_with(",", |(loader, _), f| f(&format_args!("\"{loader}\"")));
+        self.project_versions(project, Some(minecraft), loaders)
+            .await
+    }
+
+    async fn get_all_project_versions(
+        &self,
+        project: &str,
+        loaders: &HashMap<Loader, String>,
+    ) -> Result<Vec<Version>> {
+        self.project_versions(project, None, loaders).await
+    }
+
+    async fn search(
+        &self,
+        query: &str,
+        project_type: ProjectType,
+        minecraft: &str,
+        loaders: &HashMap<Loader, String>,
+        limit: usize,
+    ) -> Result<SearchResults> {
+        let mut facets = vec![
+            vec![format!("project_type:{}", project_type.as_modrinth())],
+            vec![format!("versions:{minecraft}")],
+        ];
+
+        // Only mods are tagged with the loader they run on
+        if matches!(project_type, ProjectType::Mod) && !loaders.is_empty() {
+            facets.push(
+                loaders
+                    .keys()
+                    .map(|loader| format!("categories:{loader}"))
+                    .collect(),
+            );
+        }
 
         let res = self
-            .http_client
-            .get(format!(
-                "https://api.modrinth.com/v2/project/{project}/version"
-            ))
-            .query(&[
-                ("loaders", format!("[\"minecraft\", {loaders}]")),
-                ("game_versions", format!(r#"["{minecraft}"]"#)),
-            ])
-            .send()
+            .send(self.get("/search").query(&[
+                ("query", query.to_string()),
+                ("facets", serde_json::to_string(&facets)?),
+                ("limit", limit.to_string()),
+            ]))
             .await?
             .json()
             .await?;
@@ -156,34 +377,65 @@ impl Client {
         Ok(res)
     }
 
-    pub async fn get_version(
-        &self,
-        project: &str,
-        minecraft: &str,
-        loaders: &HashMap<Loader, String>,
-        version: &str,
-    ) -> Result<Version> {
-        let versions = self
-            .get_project_versions(project, minecraft, loaders)
+    async fn get_version_by_id(&self, id: &str) -> Result<Version> {
+        let res = self
+            .send(self.get(&format!("/version/{id}")))
+            .await?
+            .json()
             .await?;
 
-        let version = versions
-            .into_iter()
-            .find(|v| v.version_number == version)
-            .expect("Failed to find a valid version");
+        Ok(res)
+    }
 
-        Ok(version)
+    async fn get_versions(&self, ids: &[String]) -> Result<Vec<Version>> {
+        self.get_bulk("/versions", ids).await
     }
 
-    pub async fn get_project(&self, project: &str) -> Result<Project> {
+    async fn get_versions_from_hashes(
+        &self,
+        hashes: &[String],
+        algorithm: &str,
+    ) -> Result<HashMap<String, Version>> {
+        if hashes.is_empty() {
+            return Ok(HashMap::new());
+        }
+
         let res = self
-            .http_client
-            .get(format!("https://api.modrinth.com/v2/project/{project}"))
-            .send()
+            .send(self.post("/version_files").json(&serde_json::json!({
+                "hashes": hashes,
+                "algorithm": algorithm,
+            })))
             .await?
             .json()
             .await?;
 
         Ok(res)
     }
+
+    /// Files are served from the shared cache when possible and stored in it otherwise
+    async fn download(&self, url: &str, sha512: &str) -> Result<Vec<u8>> {
+        if let Some(data) = self.cache.get(sha512)? {
+            return Ok(data);
+        }
+
+        let data = self
+            .http_client
+            .get(url)
+            .send()
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?;
+
+        let actual = self::sha512(&data);
+        if actual != sha512 {
+            return Err(anyhow!(
+                "Hash mismatch for {url}: expected {sha512}, got {actual}"
+            ));
+        }
+
+        self.cache.insert(sha512, &data)?;
+
+        Ok(data.to_vec())
+    }
 }
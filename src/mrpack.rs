@@ -80,17 +80,29 @@ impl From<Side> for Env {
     }
 }
 
+impl From<&Env> for Side {
+    fn from(env: &Env) -> Self {
+        match (env.client.is_needed(), env.server.is_needed()) {
+            (true, false) => Side::Client,
+            (false, true) => Side::Server,
+            _ => Side::Both,
+        }
+    }
+}
+
 #[derive(Debug, DeserializeFromStr, SerializeDisplay)]
 pub enum Requirement {
     Required,
     Optional,
     Unsupported,
+    /// Reported by Modrinth for projects that never declared it, never part of an mrpack
+    Unknown,
 }
 
 impl Requirement {
     pub const fn is_needed(&self) -> bool {
         match self {
-            Self::Required | Self::Optional => true,
+            Self::Required | Self::Optional | Self::Unknown => true,
             Self::Unsupported => false,
         }
     }
@@ -104,8 +116,9 @@ impl FromStr for Requirement {
             "required" => Ok(Self::Required),
             "optional" => Ok(Self::Optional),
             "unsupported" => Ok(Self::Unsupported),
+            "unknown" => Ok(Self::Unknown),
             _ => Err(format!(
-                "Unknown requirement '{requirement}'. Supported requirements are: required, optional, unsupported",
+                "Unknown requirement '{requirement}'. Supported requirements are: required, optional, unsupported, unknown",
             )),
         }
     }
@@ -117,6 +130,7 @@ impl Display for Requirement {
             Self::Required => write!(f, "required"),
             Self::Optional => write!(f, "optional"),
             Self::Unsupported => write!(f, "unsupported"),
+            Self::Unknown => write!(f, "unknown"),
         }
     }
 }
@@ -0,0 +1,246 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use anyhow::{Result, anyhow};
+use reqwest::{RequestBuilder, Response, StatusCode, header::HeaderMap};
+use tokio::{
+    sync::Semaphore,
+    time::{Instant, sleep, sleep_until},
+};
+
+/// Requests per minute assumed until the server reports its own limit
+const DEFAULT_LIMIT: f64 = 300.0;
+
+const CONCURRENT_REQUESTS: usize = 8;
+
+const MAX_RETRIES: u32 = 5;
+
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Throttles requests to an API with a token bucket kept in sync with the
+/// `X-Ratelimit-*` headers it sends back.
+///
+/// Clones share the same bucket, so every task talking to the API draws from it.
+#[derive(Clone)]
+pub struct RateLimiter {
+    bucket: Arc<Mutex<Bucket>>,
+    permits: Arc<Semaphore>,
+}
+
+struct Bucket {
+    tokens: f64,
+    capacity: f64,
+    updated: Instant,
+    /// Set once the server reports the limit as exhausted
+    blocked_until: Option<Instant>,
+}
+
+impl Bucket {
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.capacity / 60.0).min(self.capacity);
+        self.updated = now;
+    }
+
+    /// Takes a token, or returns when to try again
+    fn take(&mut self, now: Instant) -> Result<(), Instant> {
+        if let Some(blocked_until) = self.blocked_until {
+            if blocked_until > now {
+                return Err(blocked_until);
+            }
+            self.blocked_until = None;
+        }
+
+        self.refill(now);
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            return Ok(());
+        }
+
+        let missing = 1.0 - self.tokens;
+        Err(now + Duration::from_secs_f64(missing * 60.0 / self.capacity))
+    }
+
+    fn update(&mut self, headers: &HeaderMap, now: Instant) {
+        self.refill(now);
+
+        if let Some(limit) = header(headers, "x-ratelimit-limit").filter(|limit| *limit > 0) {
+            self.capacity = limit as f64;
+        }
+
+        if let Some(remaining) = header(headers, "x-ratelimit-remaining") {
+            self.tokens = self.tokens.min(remaining as f64);
+
+            if remaining == 0 {
+                let reset = header(headers, "x-ratelimit-reset").unwrap_or(60);
+                self.blocked_until = Some(now + Duration::from_secs(reset));
+            }
+        }
+    }
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self {
+            bucket: Arc::new(Mutex::new(Bucket {
+                tokens: DEFAULT_LIMIT,
+                capacity: DEFAULT_LIMIT,
+                updated: Instant::now(),
+                blocked_until: None,
+            })),
+            permits: Arc::new(Semaphore::new(CONCURRENT_REQUESTS)),
+        }
+    }
+}
+
+impl RateLimiter {
+    async fn acquire(&self) {
+        loop {
+            let retry_at = match self.bucket.lock().unwrap().take(Instant::now()) {
+                Ok(()) => return,
+                Err(retry_at) => retry_at,
+            };
+
+            sleep_until(retry_at).await;
+        }
+    }
+
+    /// Sends a request once a token is available.
+    ///
+    /// Rate limited requests and server errors are retried with a jittered exponential backoff,
+    /// anything else that isn't successful is turned into an error.
+    pub async fn send(&self, request: RequestBuilder) -> Result<Response> {
+        let _permit = self.permits.acquire().await?;
+
+        let mut attempt = 0;
+
+        loop {
+            self.acquire().await;
+
+            let retry = request
+                .try_clone()
+                .ok_or_else(|| anyhow!("Streaming requests can't be rate limited"))?;
+
+            let delay = match retry.send().await {
+                Ok(res) => {
+                    self.bucket
+                        .lock()
+                        .unwrap()
+                        .update(res.headers(), Instant::now());
+
+                    let status = res.status();
+                    let retryable =
+                        status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error();
+
+                    if !retryable || attempt == MAX_RETRIES {
+                        return Ok(res.error_for_status()?);
+                    }
+
+                    match header(res.headers(), "retry-after") {
+                        Some(seconds) => Duration::from_secs(seconds),
+                        None => backoff(attempt),
+                    }
+                }
+                Err(err) if (err.is_connect() || err.is_timeout()) && attempt < MAX_RETRIES => {
+                    backoff(attempt)
+                }
+                Err(err) => return Err(err.into()),
+            };
+
+            tracing::debug!("Retrying request in {delay:?} (attempt {})", attempt + 1);
+
+            sleep(delay).await;
+            attempt += 1;
+        }
+    }
+}
+
+fn header(headers: &HeaderMap, name: &str) -> Option<u64> {
+    headers.get(name)?.to_str().ok()?.trim().parse().ok()
+}
+
+/// Exponential backoff starting at half a second with the upper half of it randomized, so
+/// concurrent requests that failed together don't retry together
+fn backoff(attempt: u32) -> Duration {
+    let delay = (Duration::from_millis(500) * 2u32.pow(attempt)).min(MAX_BACKOFF);
+    delay / 2 + delay.mul_f64(fastrand::f64() / 2.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    /// A bucket allowing one request per second, with `tokens` left at `now`
+    fn bucket(tokens: f64, now: Instant) -> Bucket {
+        Bucket {
+            tokens,
+            capacity: 60.0,
+            updated: now,
+            blocked_until: None,
+        }
+    }
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+
+        for (name, value) in pairs {
+            headers.insert(*name, HeaderValue::from_static(value));
+        }
+
+        headers
+    }
+
+    #[test]
+    fn refills_over_time() {
+        let now = Instant::now();
+        let mut bucket = bucket(0.0, now);
+
+        assert_eq!(bucket.take(now), Err(now + Duration::from_secs(1)));
+
+        bucket.refill(now + Duration::from_secs(10));
+        assert_eq!(bucket.tokens, 10.0);
+
+        bucket.refill(now + Duration::from_secs(600));
+        assert_eq!(bucket.tokens, 60.0);
+        assert_eq!(bucket.take(now + Duration::from_secs(600)), Ok(()));
+    }
+
+    #[test]
+    fn clamps_to_the_remaining_requests() {
+        let now = Instant::now();
+        let mut bucket = bucket(60.0, now);
+
+        bucket.update(
+            &headers(&[("x-ratelimit-limit", "300"), ("x-ratelimit-remaining", "5")]),
+            now,
+        );
+        assert_eq!(bucket.capacity, 300.0);
+        assert_eq!(bucket.tokens, 5.0);
+
+        // The server reporting more than is left locally doesn't hand out extra tokens
+        bucket.update(&headers(&[("x-ratelimit-remaining", "200")]), now);
+        assert_eq!(bucket.tokens, 5.0);
+        assert!(bucket.blocked_until.is_none());
+    }
+
+    #[test]
+    fn blocks_until_the_reset_once_exhausted() {
+        let now = Instant::now();
+        let mut bucket = bucket(60.0, now);
+        let reset = now + Duration::from_secs(20);
+
+        bucket.update(
+            &headers(&[("x-ratelimit-remaining", "0"), ("x-ratelimit-reset", "20")]),
+            now,
+        );
+        assert_eq!(bucket.blocked_until, Some(reset));
+        assert_eq!(bucket.take(now + Duration::from_secs(10)), Err(reset));
+
+        assert_eq!(bucket.take(reset), Ok(()));
+        assert!(bucket.blocked_until.is_none());
+    }
+}
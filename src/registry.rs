@@ -0,0 +1,303 @@
+use std::{collections::HashMap, future::Future};
+
+use anyhow::{Result, anyhow};
+
+use crate::{
+    ProjectType,
+    constraint::VersionReq,
+    manifest::{Loader, Strategy},
+    modrinth::{GameVersion, Project, SearchResults, Version},
+};
+
+/// A source of projects and versions commands resolve the pack against.
+///
+/// Projects, versions and files are described with Modrinth's data model, other backends
+/// translate their own into it.
+pub trait Registry: Clone + Send + Sync + 'static {
+    fn get_game_versions(&self) -> impl Future<Output = Result<Vec<GameVersion>>> + Send;
+
+    fn get_projects(&self, ids: &[String]) -> impl Future<Output = Result<Vec<Project>>> + Send;
+
+    /// Versions of a project compatible with the given minecraft version and loaders
+    fn get_project_versions(
+        &self,
+        project: &str,
+        minecraft: &str,
+        loaders: &HashMap<Loader, String>,
+    ) -> impl Future<Output = Result<Vec<Version>>> + Send;
+
+    /// Versions of a project compatible with the given loaders, whatever minecraft version
+    /// they are for
+    fn get_all_project_versions(
+        &self,
+        project: &str,
+        loaders: &HashMap<Loader, String>,
+    ) -> impl Future<Output = Result<Vec<Version>>> + Send;
+
+    /// Searches for projects of the given type that support the pack's minecraft version and
+    /// loaders, most relevant first
+    fn search(
+        &self,
+        query: &str,
+        project_type: ProjectType,
+        minecraft: &str,
+        loaders: &HashMap<Loader, String>,
+        limit: usize,
+    ) -> impl Future<Output = Result<SearchResults>> + Send;
+
+    fn get_version_by_id(&self, id: &str) -> impl Future<Output = Result<Version>> + Send;
+
+    fn get_versions(&self, ids: &[String]) -> impl Future<Output = Result<Vec<Version>>> + Send;
+
+    /// Looks up the versions files with the given hashes belong to, keyed by hash
+    fn get_versions_from_hashes(
+        &self,
+        hashes: &[String],
+        algorithm: &str,
+    ) -> impl Future<Output = Result<HashMap<String, Version>>> + Send;
+
+    /// Downloads a file and makes sure it matches the expected sha512 hash
+    fn download(&self, url: &str, sha512: &str) -> impl Future<Output = Result<Vec<u8>>> + Send;
+
+    fn get_latest_version(
+        &self,
+        project: &str,
+        minecraft: &str,
+        loaders: &HashMap<Loader, String>,
+        strategy: &Strategy,
+    ) -> impl Future<Output = Result<Version>> + Send {
+        self.get_version(project, minecraft, loaders, &VersionReq::Latest, strategy)
+    }
+
+    /// Resolves a version requirement against the versions compatible with the pack
+    fn get_version(
+        &self,
+        project: &str,
+        minecraft: &str,
+        loaders: &HashMap<Loader, String>,
+        req: &VersionReq,
+        strategy: &Strategy,
+    ) -> impl Future<Output = Result<Version>> + Send {
+        async move {
+            let versions = self
+                .get_project_versions(project, minecraft, loaders)
+                .await?;
+
+            if versions.is_empty() {
+                return Err(anyhow!("No compatible versions found for {project}"));
+            }
+
+            req.select(versions, minecraft, strategy)
+                .ok_or_else(|| match req {
+                    VersionReq::Latest => anyhow!(
+                        "No compatible versions of {project} match the '{strategy}' strategy"
+                    ),
+                    _ => anyhow!("No compatible versions of {project} match '{req}'"),
+                })
+        }
+    }
+}
+
+/// An in-memory registry for tests
+#[cfg(test)]
+pub mod fake {
+    use std::{
+        collections::HashMap,
+        sync::{
+            Arc,
+            atomic::{AtomicUsize, Ordering},
+        },
+    };
+
+    use anyhow::{Result, anyhow, bail};
+    use chrono::DateTime;
+
+    use super::Registry;
+    use crate::{
+        ProjectType,
+        manifest::Loader,
+        modrinth::{
+            Dependency, DependencyType, File, GameVersion, Project, SearchResults, Version,
+            VersionType,
+        },
+        mrpack::Requirement,
+    };
+
+    /// Serves a fixed set of projects and versions and counts how often versions get resolved
+    #[derive(Clone, Default)]
+    pub struct Fake {
+        /// IDs and slugs of the known mods
+        projects: Vec<(String, String)>,
+        versions: Vec<Version>,
+        lookups: Arc<AtomicUsize>,
+    }
+
+    impl Fake {
+        /// Adds a mod with the project ID `id` and the slug `slug`
+        pub fn project(mut self, id: &str, slug: &str) -> Self {
+            self.projects.push((id.to_string(), slug.to_string()));
+            self
+        }
+
+        pub fn version(mut self, version: Version) -> Self {
+            self.versions.push(version);
+            self
+        }
+
+        /// How many times versions of a project were listed
+        pub fn lookups(&self) -> usize {
+            self.lookups.load(Ordering::SeqCst)
+        }
+
+        fn project_id(&self, project: &str) -> Option<&str> {
+            self.projects
+                .iter()
+                .find(|(id, slug)| id == project || slug == project)
+                .map(|(id, _)| id.as_str())
+        }
+    }
+
+    /// A release for minecraft 1.21.1, published `day` days into 2024
+    pub fn version(id: &str, project_id: &str, version_number: &str, day: i64) -> Version {
+        Version {
+            id: id.to_string(),
+            project_id: project_id.to_string(),
+            version_number: version_number.to_string(),
+            version_type: VersionType::Release,
+            date_published: DateTime::from_timestamp(1_704_067_200 + day * 86_400, 0).unwrap(),
+            featured: false,
+            changelog: None,
+            game_versions: vec!["1.21.1".to_string()],
+            files: vec![File {
+                hashes: HashMap::from([
+                    ("sha1".to_string(), format!("{id}-sha1")),
+                    ("sha512".to_string(), format!("{id}-sha512")),
+                ]),
+                url: format!("https://example.com/{id}.jar"),
+                filename: format!("{id}.jar"),
+                primary: true,
+                size: 1,
+            }],
+            dependencies: Vec::new(),
+        }
+    }
+
+    /// A dependency on a project, or on one exact version of it when `version_id` is given
+    pub fn dependency(
+        project_id: Option<&str>,
+        version_id: Option<&str>,
+        dependency_type: DependencyType,
+    ) -> Dependency {
+        Dependency {
+            version_id: version_id.map(str::to_string),
+            project_id: project_id.map(str::to_string),
+            file_name: None,
+            dependency_type,
+        }
+    }
+
+    impl Registry for Fake {
+        async fn get_game_versions(&self) -> Result<Vec<GameVersion>> {
+            Ok(Vec::new())
+        }
+
+        async fn get_projects(&self, ids: &[String]) -> Result<Vec<Project>> {
+            Ok(self
+                .projects
+                .iter()
+                .filter(|(id, slug)| ids.contains(id) || ids.contains(slug))
+                .map(|(id, slug)| Project {
+                    id: id.clone(),
+                    slug: slug.clone(),
+                    project_type: "mod".to_string(),
+                    client_side: Requirement::Required,
+                    server_side: Requirement::Required,
+                })
+                .collect())
+        }
+
+        async fn get_project_versions(
+            &self,
+            project: &str,
+            minecraft: &str,
+            loaders: &HashMap<Loader, String>,
+        ) -> Result<Vec<Version>> {
+            let versions = self.get_all_project_versions(project, loaders).await?;
+
+            Ok(versions
+                .into_iter()
+                .filter(|version| version.game_versions.iter().any(|game| game == minecraft))
+                .collect())
+        }
+
+        async fn get_all_project_versions(
+            &self,
+            project: &str,
+            _loaders: &HashMap<Loader, String>,
+        ) -> Result<Vec<Version>> {
+            self.lookups.fetch_add(1, Ordering::SeqCst);
+
+            let project_id = self
+                .project_id(project)
+                .ok_or_else(|| anyhow!("Project {project} not found"))?;
+
+            Ok(self
+                .versions
+                .iter()
+                .filter(|version| version.project_id == project_id)
+                .cloned()
+                .collect())
+        }
+
+        async fn search(
+            &self,
+            _query: &str,
+            _project_type: ProjectType,
+            _minecraft: &str,
+            _loaders: &HashMap<Loader, String>,
+            _limit: usize,
+        ) -> Result<SearchResults> {
+            bail!("Searching isn't supported")
+        }
+
+        async fn get_version_by_id(&self, id: &str) -> Result<Version> {
+            self.versions
+                .iter()
+                .find(|version| version.id == id)
+                .cloned()
+                .ok_or_else(|| anyhow!("Version {id} not found"))
+        }
+
+        async fn get_versions(&self, ids: &[String]) -> Result<Vec<Version>> {
+            Ok(self
+                .versions
+                .iter()
+                .filter(|version| ids.contains(&version.id))
+                .cloned()
+                .collect())
+        }
+
+        async fn get_versions_from_hashes(
+            &self,
+            hashes: &[String],
+            algorithm: &str,
+        ) -> Result<HashMap<String, Version>> {
+            Ok(self
+                .versions
+                .iter()
+                .flat_map(|version| {
+                    version
+                        .files
+                        .iter()
+                        .filter_map(|file| file.hashes.get(algorithm))
+                        .filter(|hash| hashes.contains(hash))
+                        .map(|hash| (hash.clone(), version.clone()))
+                })
+                .collect())
+        }
+
+        async fn download(&self, url: &str, _sha512: &str) -> Result<Vec<u8>> {
+            bail!("Can't download {url}")
+        }
+    }
+}